@@ -33,6 +33,11 @@ impl CPU {
     }
 
     pub fn ARM_handleSWI (&mut self, bus: &mut Bus, instruction: u32) {
+        if bus.isHLEBios() {
+            self.HLE_handleSWI(bus, (instruction >> 16) & 0xFF);
+            return;
+        }
+
         println!("ARM mode SWI at address: {:08X}", self.gprs[15]-8);
         let lr = self.gprs[15] - 4;
         let cpsr = self.cpsr.getRaw();
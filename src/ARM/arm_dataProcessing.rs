@@ -45,6 +45,7 @@ impl CPU {
 
     pub fn ARM_handleDataProcessingRegister (&mut self, bus: &mut Bus, instruction: u32) {
         let shiftAmount = self.getGPR((instruction >> 8) & 0xF) & 0xFF;
+        bus.addIdleCycles(1); // Shifting by a register takes an extra internal cycle
         self.gprs[15] += 4; // PC is 3 steps ahead instead of 2 in this type of instr.
                             // We stub it by making it go an extra step ahead during operand fetch
                             // Note: rs IS NOT affected by this, as it's fetched before the PC gets incremented
@@ -74,6 +75,7 @@ impl CPU {
 
     pub fn ARM_handleDataProcessingRegisterWithFlags (&mut self, bus: &mut Bus, instruction: u32) {
         let shiftAmount = self.getGPR((instruction >> 8) & 0xF) & 0xFF;
+        bus.addIdleCycles(1); // Shifting by a register takes an extra internal cycle
         self.gprs[15] += 4; // PC is 3 steps ahead instead of 2 in this type of instr.
                             // We stub it by making it go an extra step ahead during operand fetch
                             // Note: rs IS NOT affected by this, as it's fetched before the PC gets incremented
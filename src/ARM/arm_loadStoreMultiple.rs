@@ -57,6 +57,8 @@ impl CPU {
             self.setGPR(rnIndex, sp, bus);
         }
 
+        bus.addIdleCycles(1); // 1 internal cycle to write back the last loaded register
+
         if switchToUser && !isBitSet!(instruction, 15) {
             self.changeMode(currentMode);
         }
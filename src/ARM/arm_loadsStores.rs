@@ -37,6 +37,7 @@ impl CPU {
     pub fn ARM_matchLoadStoreType (&mut self, isByte: bool, isLoad: bool, isUser: bool, rdIndex: u32, address: u32, bus: &mut Bus) {
         match isLoad {
             true => {
+                bus.addIdleCycles(1); // Loads take an extra internal cycle to write the loaded value to rd
                 match isByte {
                     true => match isUser {
                         true => todo!("[ARM] Implement LDRBT"),
@@ -84,6 +85,7 @@ impl CPU {
         }
 
         if isLoad {
+            bus.addIdleCycles(1); // Loads take an extra internal cycle to write the loaded value to rd
             if isHalfword {
                 if signExtend {
                     self.ARM_LDRSH(rdIndex, address, bus);
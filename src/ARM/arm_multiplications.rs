@@ -3,6 +3,17 @@ use crate::cpu::CPU;
 use crate::isBitSet;
 
 impl CPU {
+    // Multiplies take 1 to 4 internal cycles, depending on how many of the top bytes of the multiplier are all 0s
+    // (or all 1s, for signed multiplies), as the multiplier array terminates early
+    pub fn getMultiplyCycles (rs: u32, signed: bool) -> u32 {
+        let isTerminated = |mask: u32| rs & mask == 0 || (signed && rs & mask == mask);
+
+        if isTerminated(0xFFFFFF00) { 1 }
+        else if isTerminated(0xFFFF0000) { 2 }
+        else if isTerminated(0xFF000000) { 3 }
+        else { 4 }
+    }
+
     pub fn ARM_handleMultiply (&mut self, bus: &mut Bus, instruction: u32) {
         let rdIndex = (instruction >> 16) & 0xF;
         let rnIndex = (instruction >> 12) & 0xF;
@@ -15,8 +26,10 @@ impl CPU {
 
         let updateFlags = isBitSet!(instruction, 20);
         debug_assert!(!(updateFlags && rdIndex == 15));
+        bus.addIdleCycles(Self::getMultiplyCycles(rs, true));
 
         if isBitSet!(instruction, 21) {
+            bus.addIdleCycles(1); // The accumulate takes an extra cycle
             self.ARM_MLA (rdIndex, rm, rs, rn, updateFlags, bus);
         }
 
@@ -35,6 +48,10 @@ impl CPU {
         let updateFlags = isBitSet!(instruction, 20);
 
         let opcode = (instruction >> 21) & 0x3;
+        let isSigned = isBitSet!(opcode, 1);
+        let isAccumulate = isBitSet!(opcode, 0);
+        bus.addIdleCycles(Self::getMultiplyCycles(rs, isSigned) + 1 + isAccumulate as u32); // Long multiplies take 1 more cycle, and 1 more if accumulating
+
         match opcode {
             0 => self.ARM_UMULL(rdLoIndex, rdHiIndex, rm, rs, updateFlags, bus),
             1 => self.ARM_UMLAL(rdLoIndex, rdHiIndex, rm, rs, updateFlags, bus),
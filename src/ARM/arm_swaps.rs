@@ -10,6 +10,7 @@ impl CPU {
 
         let rn = self.getGPR(rnIndex);
         let rm = self.getGPR(rmIndex);
+        bus.addIdleCycles(1); // SWP takes 1 internal cycle on top of its load and store
 
         if isBitSet!(instruction, 22) {
             self.ARM_SWPB(rdIndex, rn, rm, bus)
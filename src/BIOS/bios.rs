@@ -0,0 +1,120 @@
+use crate::bus::Bus;
+use crate::cpu::CPU;
+
+pub const BIOS_SIZE: usize = 16 * 1024;
+pub const BIOS_IF_ADDR: u32 = 0x03007FF8; // Interrupt flags that the game's IRQ handler acknowledges for IntrWait
+
+// Opcodes making up the HLE BIOS image. SWIs never reach the BIOS in HLE mode, so it only needs the exception vectors
+// and an IRQ handler that jumps to the user handler at 0x03007FFC, same as the official BIOS
+const HLE_BIOS_CODE: [(usize, u32); 14] = [
+    (0x00, 0xEAFFFFFE), // Reset:             b .
+    (0x04, 0xE1B0F00E), // Undefined:         movs pc, lr
+    (0x08, 0xE1B0F00E), // SWI:               movs pc, lr
+    (0x0C, 0xE25EF004), // Prefetch abort:    subs pc, lr, #4
+    (0x10, 0xE25EF008), // Data abort:        subs pc, lr, #8
+    (0x14, 0xEAFFFFFE), // Reserved:          b .
+    (0x18, 0xEA000042), // IRQ:               b 0x128
+    (0x1C, 0xE25EF004), // FIQ:               subs pc, lr, #4
+
+    (0x128, 0xE92D500F), // stmfd sp!, {r0-r3, r12, lr}
+    (0x12C, 0xE3A00301), // mov r0, #0x04000000
+    (0x130, 0xE28FE000), // add lr, pc, #0
+    (0x134, 0xE510F004), // ldr pc, [r0, #-4] (0x03FFFFFC, mirror of 0x03007FFC)
+    (0x138, 0xE8BD500F), // ldmfd sp!, {r0-r3, r12, lr}
+    (0x13C, 0xE25EF004)  // subs pc, lr, #4
+];
+
+pub fn buildHLEBios() -> Vec<u8> {
+    let mut bios = vec![0; BIOS_SIZE];
+
+    for &(address, opcode) in HLE_BIOS_CODE.iter() {
+        bios[address..address + 4].copy_from_slice(&opcode.to_le_bytes());
+    }
+
+    bios
+}
+
+impl CPU {
+    // Run a SWI natively instead of jumping into the BIOS
+    pub fn HLE_handleSWI (&mut self, bus: &mut Bus, swiNum: u32) {
+        match swiNum {
+            0x00 => self.HLE_SoftReset(bus),
+            0x01 => self.HLE_RegisterRamReset(bus, self.gprs[0]),
+            0x02 => bus.halted = true, // Halt
+            0x03 => bus.halted = true, // Stop. We don't have a low power mode, so treat it as a halt
+            0x04 => self.HLE_IntrWait(bus, self.gprs[0] != 0, self.gprs[1] as u16),
+            0x05 => self.HLE_IntrWait(bus, true, 1), // VBlankIntrWait
+            0x06 => self.HLE_Div(self.gprs[0] as i32, self.gprs[1] as i32),
+            0x07 => self.HLE_Div(self.gprs[1] as i32, self.gprs[0] as i32), // DivArm, same as Div with the operands swapped
+            0x08 => self.gprs[0] = Self::HLE_Sqrt(self.gprs[0]),
+            0x09 => self.HLE_ArcTan(),
+            0x0A => self.gprs[0] = Self::HLE_ArcTan2(self.gprs[0] as i32, self.gprs[1] as i32) as u32,
+            0x0B => self.HLE_CpuSet(bus),
+            0x0C => self.HLE_CpuFastSet(bus),
+            0x0D => self.gprs[0] = 0xBAAE187F, // GetBiosChecksum, returns the checksum of the GBA BIOS
+            0x0E => self.HLE_BgAffineSet(bus),
+            0x0F => self.HLE_ObjAffineSet(bus),
+            0x10 => self.HLE_BitUnPack(bus),
+            0x11..=0x18 => self.HLE_Decompress(bus, swiNum), // LZ77, Huffman, RLE and Diff filters
+            _ => println!("Unimplemented HLE SWI {:02X} at PC: {:08X}", swiNum, self.getSWIAddress())
+        }
+    }
+
+    // Address of the SWI opcode being executed
+    fn getSWIAddress (&self) -> u32 {
+        if self.isInARMState() {
+            self.gprs[15] - 8
+        }
+
+        else {
+            self.gprs[15] - 4
+        }
+    }
+
+    fn HLE_SoftReset (&mut self, bus: &mut Bus) {
+        let returnToEWRAM = bus.read8(0x03007FFA) != 0; // Decides whether to restart from EWRAM or ROM
+        for address in (0x03007E00..0x03008000).step_by(4) {
+            bus.write32(address, 0);
+        }
+
+        self.setCPSR(0x13); // Reset the SVC and IRQ banks
+        self.gprs[13] = 0x03007FE0;
+        self.gprs[14] = 0;
+        self.spsr.setRaw(0);
+
+        self.setCPSR(0x12);
+        self.gprs[13] = 0x03007FA0;
+        self.gprs[14] = 0;
+        self.spsr.setRaw(0);
+
+        self.setCPSR(0x1F); // Return in System mode, in ARM state
+        for i in 0..13 {
+            self.gprs[i] = 0;
+        }
+
+        self.gprs[13] = 0x03007F00;
+        self.gprs[14] = 0;
+        self.setGPR(15, if returnToEWRAM {0x02000000} else {0x08000000}, bus);
+    }
+
+    fn HLE_IntrWait (&mut self, bus: &mut Bus, discardOldFlags: bool, flags: u16) {
+        bus.ime = true; // IntrWait forcibly enables IME
+        let mut biosIF = bus.read16(BIOS_IF_ADDR);
+
+        if discardOldFlags && !self.isInIntrWait { // Only discard flags when first entering the SWI, not when resuming it
+            biosIF &= !flags;
+        }
+
+        if (biosIF & flags) != 0 { // One of the interrupts we're waiting for happened, acknowledge it and return
+            bus.write16(BIOS_IF_ADDR, biosIF & !flags);
+            self.isInIntrWait = false;
+            return;
+        }
+
+        // Halt, then run the SWI again once the IRQ handler returns here
+        bus.write16(BIOS_IF_ADDR, biosIF);
+        bus.halted = true;
+        self.isInIntrWait = true;
+        self.setGPR(15, self.getSWIAddress(), bus);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::bus::Bus;
+use crate::cpu::CPU;
+use std::f64::consts::PI;
+
+// The rotation angle is a 16-bit value where 0x10000 is a full turn. The BIOS only uses the top 8 bits
+fn getAngle (angle: u16) -> f64 {
+    (angle >> 8) as f64 / 128.0 * PI
+}
+
+impl CPU {
+    // Calculate BG rotation/scaling parameters from a list of (center, scale, angle) structs
+    pub fn HLE_BgAffineSet (&mut self, bus: &mut Bus) {
+        let mut source = self.gprs[0];
+        let mut dest = self.gprs[1];
+        let count = self.gprs[2];
+
+        for _ in 0..count {
+            let originalCenterX = bus.read32(source) as i32 as f64 / 256.0; // 20.8 fixed point
+            let originalCenterY = bus.read32(source + 4) as i32 as f64 / 256.0;
+            let displayCenterX = bus.read16(source + 8) as i16 as f64;
+            let displayCenterY = bus.read16(source + 10) as i16 as f64;
+            let scaleX = bus.read16(source + 12) as i16 as f64 / 256.0; // 8.8 fixed point
+            let scaleY = bus.read16(source + 14) as i16 as f64 / 256.0;
+            let angle = getAngle(bus.read16(source + 16));
+            source += 20;
+
+            let pa = angle.cos() * scaleX;
+            let pb = -angle.sin() * scaleX;
+            let pc = angle.sin() * scaleY;
+            let pd = angle.cos() * scaleY;
+            let startX = originalCenterX - (pa * displayCenterX + pb * displayCenterY);
+            let startY = originalCenterY - (pc * displayCenterX + pd * displayCenterY);
+
+            bus.write16(dest, (pa * 256.0) as i16 as u16);
+            bus.write16(dest + 2, (pb * 256.0) as i16 as u16);
+            bus.write16(dest + 4, (pc * 256.0) as i16 as u16);
+            bus.write16(dest + 6, (pd * 256.0) as i16 as u16);
+            bus.write32(dest + 8, (startX * 256.0) as i32 as u32);
+            bus.write32(dest + 12, (startY * 256.0) as i32 as u32);
+            dest += 16;
+        }
+    }
+
+    // Calculate OBJ rotation/scaling parameters. r3 is the distance between each parameter in the destination (8 for OAM)
+    pub fn HLE_ObjAffineSet (&mut self, bus: &mut Bus) {
+        let mut source = self.gprs[0];
+        let mut dest = self.gprs[1];
+        let count = self.gprs[2];
+        let offset = self.gprs[3];
+
+        for _ in 0..count {
+            let scaleX = bus.read16(source) as i16 as f64 / 256.0;
+            let scaleY = bus.read16(source + 2) as i16 as f64 / 256.0;
+            let angle = getAngle(bus.read16(source + 4));
+            source += 8;
+
+            let pa = angle.cos() * scaleX;
+            let pb = -angle.sin() * scaleX;
+            let pc = angle.sin() * scaleY;
+            let pd = angle.cos() * scaleY;
+
+            bus.write16(dest, (pa * 256.0) as i16 as u16);
+            bus.write16(dest + offset, (pb * 256.0) as i16 as u16);
+            bus.write16(dest + offset * 2, (pc * 256.0) as i16 as u16);
+            bus.write16(dest + offset * 3, (pd * 256.0) as i16 as u16);
+            dest += offset * 4;
+        }
+    }
+}
@@ -0,0 +1,235 @@
+// Compressors producing data in the formats the BIOS decompression SWIs understand.
+// Output is padded to a multiple of 4 bytes, since games keep compressed data word-aligned
+
+use std::collections::VecDeque;
+
+fn makeHeader (kind: u8, size: usize) -> Vec<u8> {
+    assert!(size < (1 << 24), "Data is too big to compress");
+    (((size as u32) << 8) | kind as u32).to_le_bytes().to_vec()
+}
+
+fn padToWord (data: &mut Vec<u8>) {
+    while data.len() % 4 != 0 {
+        data.push(0);
+    }
+}
+
+// Greedy LZ77. Matches never start at the previous byte (displacement 1), so the output is also safe for the VRAM SWI
+pub fn compressLZ77 (data: &[u8]) -> Vec<u8> {
+    let mut out = makeHeader(0x10, data.len());
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let flagIndex = out.len();
+        out.push(0);
+
+        for i in (0..8).rev() {
+            if pos >= data.len() {
+                break;
+            }
+
+            let mut bestLength = 0;
+            let mut bestDisp = 0;
+            for disp in 2..=pos.min(0x1000) {
+                let length = (0..18.min(data.len() - pos)).take_while(|&j| data[pos + j] == data[pos + j - disp]).count();
+                if length > bestLength {
+                    bestLength = length;
+                    bestDisp = disp;
+                }
+            }
+
+            if bestLength >= 3 {
+                out[flagIndex] |= 1 << i;
+                out.push((((bestLength - 3) << 4) | ((bestDisp - 1) >> 8)) as u8);
+                out.push((bestDisp - 1) as u8);
+                pos += bestLength;
+            }
+
+            else {
+                out.push(data[pos]);
+                pos += 1;
+            }
+        }
+    }
+
+    padToWord(&mut out);
+    out
+}
+
+pub fn compressRLE (data: &[u8]) -> Vec<u8> {
+    let mut out = makeHeader(0x30, data.len());
+    let mut literals: Vec<u8> = vec![];
+    let mut pos = 0;
+
+    let flushLiterals = |out: &mut Vec<u8>, literals: &mut Vec<u8>| {
+        for chunk in literals.chunks(128) {
+            out.push(chunk.len() as u8 - 1);
+            out.extend_from_slice(chunk);
+        }
+
+        literals.clear();
+    };
+
+    while pos < data.len() {
+        let runLength = data[pos..].iter().take(130).take_while(|&&byte| byte == data[pos]).count();
+
+        if runLength >= 3 {
+            flushLiterals(&mut out, &mut literals);
+            out.push(0x80 | (runLength - 3) as u8);
+            out.push(data[pos]);
+            pos += runLength;
+        }
+
+        else {
+            literals.push(data[pos]);
+            pos += 1;
+        }
+    }
+
+    flushLiterals(&mut out, &mut literals);
+    padToWord(&mut out);
+    out
+}
+
+enum HuffmanNode {
+    Leaf(u8),
+    Internal(Box<HuffmanNode>, Box<HuffmanNode>)
+}
+
+impl HuffmanNode {
+    fn isLeaf(&self) -> bool {
+        matches!(self, HuffmanNode::Leaf(_))
+    }
+
+    fn collectCodes(&self, code: Vec<bool>, codes: &mut [Vec<bool>]) {
+        match self {
+            HuffmanNode::Leaf(val) => codes[*val as usize] = code,
+            HuffmanNode::Internal(left, right) => {
+                let mut leftCode = code.clone();
+                let mut rightCode = code;
+                leftCode.push(false);
+                rightCode.push(true);
+                left.collectCodes(leftCode, codes);
+                right.collectCodes(rightCode, codes);
+            }
+        }
+    }
+}
+
+// Huffman with 4 or 8-bit data units. The tree is laid out breadth-first, which keeps the 6-bit child offsets in range
+// as long as the data doesn't use too many different symbols
+pub fn compressHuffman (data: &[u8], dataBits: u32) -> Vec<u8> {
+    assert!(dataBits == 4 || dataBits == 8, "Huffman data units must be 4 or 8 bits");
+    assert!(data.len() % 4 == 0, "Huffman data must be a multiple of 4 bytes long");
+
+    let symbols: Vec<u8> = if dataBits == 8 {
+        data.to_vec()
+    } else { // Nibbles are unpacked low nibble first
+        data.iter().flat_map(|&byte| vec![byte & 0xF, byte >> 4]).collect()
+    };
+
+    // Build the tree by repeatedly merging the 2 least frequent nodes
+    let mut frequencies = [0_usize; 256];
+    for &symbol in &symbols {
+        frequencies[symbol as usize] += 1;
+    }
+
+    let mut nodes: Vec<(usize, HuffmanNode)> = (0..256).filter(|&i| frequencies[i] != 0)
+                                                      .map(|i| (frequencies[i], HuffmanNode::Leaf(i as u8)))
+                                                      .collect();
+
+    if nodes.len() == 1 { // A tree needs at least 2 leaves
+        let val = match nodes[0].1 { HuffmanNode::Leaf(val) => val, _ => unreachable!() };
+        nodes.push((0, HuffmanNode::Leaf(val)));
+    }
+
+    while nodes.len() > 1 {
+        nodes.sort_by(|a, b| b.0.cmp(&a.0));
+        let (rightFreq, right) = nodes.pop().unwrap();
+        let (leftFreq, left) = nodes.pop().unwrap();
+        nodes.push((leftFreq + rightFreq, HuffmanNode::Internal(Box::new(left), Box::new(right))));
+    }
+
+    let root = nodes.pop().unwrap().1;
+    let mut codes = vec![vec![]; 256];
+    root.collectCodes(vec![], &mut codes);
+
+    // The tree table starts with its size byte, followed by the root node and then pairs of child nodes
+    let mut tree = vec![0_u8; 2];
+    let mut queue = VecDeque::new();
+    queue.push_back((&root, 1_usize));
+
+    while let Some((node, index)) = queue.pop_front() {
+        if let HuffmanNode::Internal(left, right) = node {
+            let childIndex = tree.len();
+            let offset = (childIndex - (index & !1) - 2) / 2;
+            assert!(offset < 64, "Huffman tree is too wide to encode");
+
+            tree.extend_from_slice(&[0, 0]);
+            tree[index] = offset as u8 | ((left.isLeaf() as u8) << 7) | ((right.isLeaf() as u8) << 6);
+
+            for &(child, childIndex) in &[(left.as_ref(), childIndex), (right.as_ref(), childIndex + 1)] {
+                match child {
+                    HuffmanNode::Leaf(val) => tree[childIndex] = *val,
+                    _ => queue.push_back((child, childIndex))
+                }
+            }
+        }
+    }
+
+    padToWord(&mut tree); // Keep the bitstream word-aligned
+    tree[0] = (tree.len() / 2 - 1) as u8;
+
+    let mut out = makeHeader(0x20 | dataBits as u8, data.len());
+    out.extend_from_slice(&tree);
+
+    // The bitstream is stored in words, and each word is read from the MSB down
+    let mut word = 0_u32;
+    let mut bits = 0;
+    for &symbol in &symbols {
+        for &bit in &codes[symbol as usize] {
+            word |= (bit as u32) << (31 - bits);
+            bits += 1;
+
+            if bits == 32 {
+                out.extend_from_slice(&word.to_le_bytes());
+                word = 0;
+                bits = 0;
+            }
+        }
+    }
+
+    if bits != 0 {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+
+    out
+}
+
+pub fn filterDiff8 (data: &[u8]) -> Vec<u8> {
+    let mut out = makeHeader(0x81, data.len());
+    let mut previous = 0_u8;
+
+    for &byte in data {
+        out.push(byte.wrapping_sub(previous));
+        previous = byte;
+    }
+
+    padToWord(&mut out);
+    out
+}
+
+pub fn filterDiff16 (data: &[u8]) -> Vec<u8> {
+    assert!(data.len() % 2 == 0, "Diff16 data must be a multiple of 2 bytes long");
+    let mut out = makeHeader(0x82, data.len());
+    let mut previous = 0_u16;
+
+    for halfword in data.chunks(2) {
+        let val = u16::from_le_bytes([halfword[0], halfword[1]]);
+        out.extend_from_slice(&val.wrapping_sub(previous).to_le_bytes());
+        previous = val;
+    }
+
+    padToWord(&mut out);
+    out
+}
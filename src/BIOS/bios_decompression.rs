@@ -0,0 +1,355 @@
+use crate::bus::Bus;
+use crate::cpu::CPU;
+use crate::isBitSet;
+
+// The decompressors read their input through a closure that takes an offset from the start of the compressed data,
+// so they can run on the bus as well as on plain buffers
+
+fn read32 (read: &mut impl FnMut(u32) -> u8, offset: u32) -> u32 {
+    (read(offset) as u32) | ((read(offset + 1) as u32) << 8) | ((read(offset + 2) as u32) << 16) | ((read(offset + 3) as u32) << 24)
+}
+
+// All compressed data starts with a 32-bit header. The decompressed size is in the top 24 bits
+fn getDecompressedSize (read: &mut impl FnMut(u32) -> u8) -> usize {
+    (read32(read, 0) >> 8) as usize
+}
+
+pub fn decompressLZ77 (mut read: impl FnMut(u32) -> u8) -> Vec<u8> {
+    let size = getDecompressedSize(&mut read);
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 4;
+
+    while out.len() < size {
+        let flags = read(pos); // Each flag bit says whether the next block is a literal byte (0) or a back-reference (1)
+        pos += 1;
+
+        for i in (0..8).rev() {
+            if out.len() >= size {
+                break;
+            }
+
+            if isBitSet!(flags, i) {
+                let info = ((read(pos) as usize) << 8) | read(pos + 1) as usize;
+                let length = (info >> 12) + 3;
+                let disp = (info & 0xFFF) + 1;
+                pos += 2;
+
+                for _ in 0..length {
+                    let byte = match out.len().checked_sub(disp) {
+                        Some(index) => out[index],
+                        None => 0 // Reference from before the start of the output
+                    };
+
+                    out.push(byte);
+                }
+            }
+
+            else {
+                out.push(read(pos));
+                pos += 1;
+            }
+        }
+    }
+
+    out.truncate(size);
+    out
+}
+
+pub fn decompressHuffman (mut read: impl FnMut(u32) -> u8) -> Vec<u8> {
+    let size = getDecompressedSize(&mut read);
+    let dataBits = (read(0) & 0xF) as u32; // 4 or 8
+    let treeSize = (read(4) as u32 + 1) * 2;
+    let root = 5;
+
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 4 + treeSize; // The bitstream comes after the tree, in 32-bit units read from MSB to LSB
+    let mut node = root;
+    let mut unpacked = 0_u32; // Decoded values are packed into words, starting from the LSB
+    let mut unpackedBits = 0;
+
+    while out.len() < size {
+        let bits = read32(&mut read, pos);
+        pos += 4;
+
+        for i in (0..32).rev() {
+            let goRight = isBitSet!(bits, i);
+            let nodeVal = read(node) as u32;
+            let child = (node & !1) + (nodeVal & 0x3F) * 2 + 2 + goRight as u32;
+            let isLeaf = if goRight { isBitSet!(nodeVal, 6) } else { isBitSet!(nodeVal, 7) };
+
+            if !isLeaf {
+                node = child;
+                continue;
+            }
+
+            unpacked |= (read(child) as u32 & ((1 << dataBits) - 1)) << unpackedBits;
+            unpackedBits += dataBits;
+            node = root;
+
+            if unpackedBits == 32 {
+                out.extend_from_slice(&unpacked.to_le_bytes());
+                unpacked = 0;
+                unpackedBits = 0;
+
+                if out.len() >= size {
+                    break;
+                }
+            }
+        }
+    }
+
+    out.truncate(size);
+    out
+}
+
+pub fn decompressRLE (mut read: impl FnMut(u32) -> u8) -> Vec<u8> {
+    let size = getDecompressedSize(&mut read);
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 4;
+
+    while out.len() < size {
+        let flag = read(pos);
+        pos += 1;
+
+        if isBitSet!(flag, 7) { // A run of 3 to 130 copies of the same byte
+            let length = (flag & 0x7F) as usize + 3;
+            let byte = read(pos);
+            pos += 1;
+            out.extend(std::iter::repeat(byte).take(length));
+        }
+
+        else { // 1 to 128 uncompressed bytes
+            let length = (flag & 0x7F) as u32 + 1;
+            for i in 0..length {
+                out.push(read(pos + i));
+            }
+
+            pos += length;
+        }
+    }
+
+    out.truncate(size);
+    out
+}
+
+// Each byte is stored as the difference from the previous one
+pub fn unfilterDiff8 (mut read: impl FnMut(u32) -> u8) -> Vec<u8> {
+    let size = getDecompressedSize(&mut read);
+    let mut out = Vec::with_capacity(size);
+    let mut current = 0_u8;
+
+    for i in 0..size as u32 {
+        current = current.wrapping_add(read(4 + i));
+        out.push(current);
+    }
+
+    out
+}
+
+// Same as Diff8, but with halfwords
+pub fn unfilterDiff16 (mut read: impl FnMut(u32) -> u8) -> Vec<u8> {
+    let size = getDecompressedSize(&mut read) & !1;
+    let mut out = Vec::with_capacity(size);
+    let mut current = 0_u16;
+
+    for i in (0..size as u32).step_by(2) {
+        let diff = (read(4 + i) as u16) | ((read(5 + i) as u16) << 8);
+        current = current.wrapping_add(diff);
+        out.extend_from_slice(&current.to_le_bytes());
+    }
+
+    out
+}
+
+// Expand srcWidth-bit values into destWidth-bit ones, adding dataOffset to them (and to zeroes too if addOffsetToZero is set)
+pub fn bitUnPack (mut read: impl FnMut(u32) -> u8, length: u32, srcWidth: u32, destWidth: u32, dataOffset: u32, addOffsetToZero: bool) -> Vec<u8> {
+    let mut out = vec![];
+    let srcMask = (1_u32 << srcWidth) - 1;
+    let mut packed = 0_u32;
+    let mut packedBits = 0;
+
+    for i in 0..length {
+        let byte = read(i) as u32;
+
+        for bit in (0..8).step_by(srcWidth as usize) {
+            let mut val = (byte >> bit) & srcMask;
+            if val != 0 || addOffsetToZero {
+                val = val.wrapping_add(dataOffset);
+            }
+
+            if destWidth < 32 {
+                val &= (1 << destWidth) - 1;
+            }
+
+            packed |= val << packedBits;
+            packedBits += destWidth;
+
+            if packedBits == 32 {
+                out.extend_from_slice(&packed.to_le_bytes());
+                packed = 0;
+                packedBits = 0;
+            }
+        }
+    }
+
+    out
+}
+
+impl CPU {
+    // Write decompressed data to the bus in units of 1, 2 or 4 bytes. VRAM can't take 8-bit writes, so the VRAM SWIs use halfwords
+    fn writeDecompressedData (bus: &mut Bus, dest: u32, data: &[u8], unitSize: u32) {
+        for (i, unit) in data.chunks(unitSize as usize).enumerate() {
+            let address = dest + i as u32 * unitSize;
+            let mut val = 0_u32;
+            for (byteNum, &byte) in unit.iter().enumerate() {
+                val |= (byte as u32) << (byteNum * 8);
+            }
+
+            match unitSize {
+                1 => bus.write8(address, val as u8),
+                2 => bus.write16(address, val as u16),
+                _ => bus.write32(address, val)
+            }
+        }
+    }
+
+    // Decompression SWIs (0x11-0x18) all take the source in r0 and the destination in r1
+    pub fn HLE_Decompress (&mut self, bus: &mut Bus, swiNum: u32) {
+        let source = self.gprs[0];
+        let dest = self.gprs[1];
+        let read = |bus: &mut Bus, offset: u32| bus.read8(source.wrapping_add(offset));
+
+        let (data, unitSize) = match swiNum {
+            0x11 => (decompressLZ77(|offset| read(bus, offset)), 1),
+            0x12 => (decompressLZ77(|offset| read(bus, offset)), 2),
+            0x13 => (decompressHuffman(|offset| read(bus, offset)), 4),
+            0x14 => (decompressRLE(|offset| read(bus, offset)), 1),
+            0x15 => (decompressRLE(|offset| read(bus, offset)), 2),
+            0x16 => (unfilterDiff8(|offset| read(bus, offset)), 1),
+            0x17 => (unfilterDiff8(|offset| read(bus, offset)), 2),
+            _    => (unfilterDiff16(|offset| read(bus, offset)), 2)
+        };
+
+        Self::writeDecompressedData(bus, dest, &data, unitSize);
+    }
+
+    pub fn HLE_BitUnPack (&mut self, bus: &mut Bus) {
+        let source = self.gprs[0];
+        let dest = self.gprs[1];
+        let info = self.gprs[2]; // Pointer to the unpacking parameters
+
+        let length = bus.read16(info) as u32;
+        let srcWidth = bus.read8(info + 2) as u32;
+        let destWidth = bus.read8(info + 3) as u32;
+        let offset = bus.read32(info + 4);
+
+        if ![1, 2, 4, 8].contains(&srcWidth) || ![1, 2, 4, 8, 16, 32].contains(&destWidth) {
+            println!("Invalid BitUnPack widths: {} -> {}", srcWidth, destWidth);
+            return;
+        }
+
+        let data = bitUnPack(|i| bus.read8(source.wrapping_add(i)), length, srcWidth, destWidth, offset & 0x7FFFFFFF, isBitSet!(offset, 31));
+        Self::writeDecompressedData(bus, dest, &data, 4);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BIOS::bios_compression::*;
+
+    fn sampleData() -> Vec<u8> { // Runs, repeated patterns and noise, so every compressor has something to chew on
+        let mut data = vec![0x42; 300];
+        for i in 0..1500_u32 {
+            data.push((i % 7 + (i / 64) % 5) as u8 * 3);
+        }
+
+        for i in 0..500_u32 {
+            data.push((i.wrapping_mul(2654435761) >> 24) as u8 & 0xF);
+        }
+
+        data.extend(std::iter::repeat(0xAB).take(777));
+        data.push(0x99); // Odd size
+        data
+    }
+
+    fn reader(buffer: &[u8]) -> impl FnMut(u32) -> u8 + '_ {
+        move |offset| buffer.get(offset as usize).copied().unwrap_or(0)
+    }
+
+    #[test]
+    fn lz77_round_trip() {
+        let data = sampleData();
+        let compressed = compressLZ77(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompressLZ77(reader(&compressed)), data);
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let data = sampleData();
+        let compressed = compressRLE(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompressRLE(reader(&compressed)), data);
+    }
+
+    #[test]
+    fn huffman_round_trip() {
+        let mut data = sampleData();
+        data.truncate(data.len() & !3); // Huffman data is decompressed in whole words
+
+        for &dataBits in &[4, 8] {
+            let compressed = compressHuffman(&data, dataBits);
+            assert_eq!(decompressHuffman(reader(&compressed)), data);
+        }
+    }
+
+    #[test]
+    fn huffman_single_symbol() {
+        let data = vec![0x77; 64];
+        assert_eq!(decompressHuffman(reader(&compressHuffman(&data, 4))), data);
+    }
+
+    #[test]
+    fn diff_filters_round_trip() {
+        let mut data = sampleData();
+        assert_eq!(unfilterDiff8(reader(&filterDiff8(&data))), data);
+
+        data.truncate(data.len() & !1);
+        assert_eq!(unfilterDiff16(reader(&filterDiff16(&data))), data);
+    }
+
+    #[test]
+    fn bit_unpack_1bpp_to_4bpp() {
+        let font = [0b1000_0001, 0b0000_0110];
+        let unpacked = bitUnPack(reader(&font), 2, 1, 4, 2, false);
+        assert_eq!(unpacked, vec![0x03, 0x00, 0x00, 0x30, 0x30, 0x03, 0x00, 0x00]);
+
+        let unpackedWithZeroes = bitUnPack(reader(&font), 1, 1, 8, 1, true);
+        assert_eq!(unpackedWithZeroes, vec![2, 1, 1, 1, 1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn vram_swis_write_halfwords() { // VRAM ignores 8-bit writes, so an odd-sized output only lands if it's written as halfwords
+        let data = [0x11, 0x22, 0x33, 0x44, 0x55];
+
+        for (swiNum, compressed) in [(0x12, compressLZ77(&data)), (0x15, compressRLE(&data))].iter() {
+            let mut rom = vec![0; 0x1000];
+            rom.extend_from_slice(compressed);
+
+            let mut bus = Bus::new(crate::mem::Memory::fromBytes(rom, None, None).unwrap());
+            let mut cpu = CPU::new();
+            for i in 0..8 {
+                bus.ppu.VRAM[i] = 0xEE;
+            }
+
+            cpu.gprs[0] = 0x08001000;
+            cpu.gprs[1] = 0x06000000;
+            cpu.HLE_Decompress(&mut bus, *swiNum);
+
+            assert_eq!(&bus.ppu.VRAM[0..4], &[0x11, 0x22, 0x33, 0x44]);
+            assert_eq!(bus.read16(0x06000004), 0x0055); // The last halfword is padded with 0
+            assert_eq!(bus.ppu.VRAM[6], 0xEE);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::cpu::CPU;
+
+impl CPU {
+    pub fn HLE_Div (&mut self, numerator: i32, denominator: i32) {
+        if denominator == 0 { // The real BIOS hangs for most divisions by 0. No game should do this, so return something sane
+            self.gprs[0] = if numerator < 0 {-1_i32 as u32} else {1};
+            self.gprs[1] = numerator as u32;
+            self.gprs[3] = 1;
+            return;
+        }
+
+        let quotient = numerator.wrapping_div(denominator); // Wrapping handles 0x80000000 / -1
+        self.gprs[0] = quotient as u32;
+        self.gprs[1] = numerator.wrapping_rem(denominator) as u32;
+        self.gprs[3] = quotient.wrapping_abs() as u32;
+    }
+
+    pub fn HLE_Sqrt (val: u32) -> u32 { // Integer square root, rounded down
+        let mut res = 0_u32;
+        let mut remainder = val;
+        let mut bit = 1_u32 << 30;
+
+        while bit > remainder {
+            bit >>= 2;
+        }
+
+        while bit != 0 {
+            if remainder >= res + bit {
+                remainder -= res + bit;
+                res = (res >> 1) + bit;
+            }
+
+            else {
+                res >>= 1;
+            }
+
+            bit >>= 2;
+        }
+
+        res
+    }
+
+    // The BIOS approximates arctan with a polynomial. The input is a 1.14 fixed point tangent,
+    // the result is an angle in the range -0x4000 to 0x4000 (-pi/2 to pi/2)
+    fn calculateArcTan (tan: i32) -> (i32, i32) {
+        let a = -(tan.wrapping_mul(tan) >> 14);
+        let mut b = (0xA9_i32.wrapping_mul(a) >> 14) + 0x390;
+
+        for &coefficient in &[0x91C, 0xFB6, 0x16AA, 0x2081, 0x3651, 0xA2F9] {
+            b = (b.wrapping_mul(a) >> 14) + coefficient;
+        }
+
+        (tan.wrapping_mul(b) >> 16, a)
+    }
+
+    pub fn HLE_ArcTan (&mut self) {
+        let (res, a) = Self::calculateArcTan(self.gprs[0] as i32);
+        self.gprs[0] = res as u32;
+        self.gprs[1] = a as u32;
+    }
+
+    // Angle of the (x, y) vector, in the range 0 to 0xFFFF (0 to 2pi)
+    pub fn HLE_ArcTan2 (x: i32, y: i32) -> u16 {
+        let arcTan = |tan: i32| Self::calculateArcTan(tan).0;
+
+        if y == 0 {
+            return if x >= 0 {0} else {0x8000};
+        }
+
+        if x == 0 {
+            return if y >= 0 {0x4000} else {0xC000};
+        }
+
+        let res = if y >= 0 {
+            if x >= 0 && x >= y { arcTan((y << 14) / x) }
+            else if x < 0 && -x >= y { arcTan((y << 14) / x) + 0x8000 }
+            else { 0x4000 - arcTan((x << 14) / y) }
+        }
+
+        else {
+            if x <= 0 && -x > -y { arcTan((y << 14) / x) + 0x8000 }
+            else if x > 0 && x >= -y { arcTan((y << 14) / x) + 0x10000 }
+            else { 0xC000 - arcTan((x << 14) / y) }
+        };
+
+        res as u16
+    }
+}
@@ -0,0 +1,86 @@
+use crate::bus::Bus;
+use crate::cpu::CPU;
+use crate::isBitSet;
+
+impl CPU {
+    pub fn HLE_CpuSet (&mut self, bus: &mut Bus) {
+        let mut source = self.gprs[0];
+        let mut dest = self.gprs[1];
+        let control = self.gprs[2];
+        let count = control & 0x1FFFFF;
+        let isFill = isBitSet!(control, 24); // Fill the destination with the first unit of the source instead of copying
+        let is32Bit = isBitSet!(control, 26);
+
+        if is32Bit {
+            source &= !3;
+            dest &= !3;
+            let fillValue = bus.read32(source);
+
+            for _ in 0..count {
+                let val = if isFill {fillValue} else {bus.read32(source)};
+                bus.write32(dest, val);
+                if !isFill {source += 4};
+                dest += 4;
+            }
+        }
+
+        else {
+            source &= !1;
+            dest &= !1;
+            let fillValue = bus.read16(source);
+
+            for _ in 0..count {
+                let val = if isFill {fillValue} else {bus.read16(source)};
+                bus.write16(dest, val);
+                if !isFill {source += 2};
+                dest += 2;
+            }
+        }
+    }
+
+    pub fn HLE_CpuFastSet (&mut self, bus: &mut Bus) {
+        let mut source = self.gprs[0] & !3;
+        let mut dest = self.gprs[1] & !3;
+        let control = self.gprs[2];
+        let count = ((control & 0x1FFFFF) + 7) & !7; // CpuFastSet works in blocks of 8 words, so the count is rounded up
+        let isFill = isBitSet!(control, 24);
+        let fillValue = bus.read32(source);
+
+        for _ in 0..count {
+            let val = if isFill {fillValue} else {bus.read32(source)};
+            bus.write32(dest, val);
+            if !isFill {source += 4};
+            dest += 4;
+        }
+    }
+
+    pub fn HLE_RegisterRamReset (&mut self, bus: &mut Bus, flags: u32) {
+        let clearRange = |bus: &mut Bus, start: u32, end: u32| {
+            for address in (start..end).step_by(4) {
+                bus.write32(address, 0);
+            }
+        };
+
+        let clearIORange = |bus: &mut Bus, start: u32, end: u32| { // Some IO registers only accept 16-bit writes
+            for address in (start..end).step_by(2) {
+                bus.write16(address, 0);
+            }
+        };
+
+        bus.write16(0x4000000, 0x80); // DISPCNT always gets set to forced blank
+
+        if isBitSet!(flags, 0) { clearRange(bus, 0x02000000, 0x02040000) } // EWRAM
+        if isBitSet!(flags, 1) { clearRange(bus, 0x03000000, 0x03007E00) } // IWRAM, except for the last 0x200 bytes (stacks and BIOS variables)
+        if isBitSet!(flags, 2) { clearRange(bus, 0x05000000, 0x05000400) } // Palette RAM
+        if isBitSet!(flags, 3) { clearRange(bus, 0x06000000, 0x06018000) } // VRAM
+        if isBitSet!(flags, 4) { clearRange(bus, 0x07000000, 0x07000400) } // OAM
+        if isBitSet!(flags, 5) { clearIORange(bus, 0x4000120, 0x4000160) } // Serial registers
+        if isBitSet!(flags, 6) { clearIORange(bus, 0x4000060, 0x40000B0) } // Sound registers
+
+        if isBitSet!(flags, 7) { // All other registers
+            clearIORange(bus, 0x4000002, 0x4000060); // Display
+            clearIORange(bus, 0x40000B0, 0x4000120); // DMA and timers
+            clearIORange(bus, 0x4000200, 0x400020A); // Interrupt control and WAITCNT
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod bios;
+mod bios_math;
+mod bios_memory;
+mod bios_affine;
+mod bios_decompression;
+pub mod bios_compression;
@@ -65,6 +65,10 @@ impl Bus {
             if channel == 3 { wordCount = 0x10000 }
         }
 
+        if channel == 3 && self.isEEPROMAccess(dest) { // Use the request length to find the EEPROM's size
+            self.mem.backup.getEEPROM().unwrap().detectSize(wordCount);
+        }
+
         //println!("Firing DMA from channel {}. Word Count: {:04X}\nSource: {:08X}  Destination: {:08X}", channel, wordCount, source, dest);
 
         if controlReg.is32Bit() { // If the transfer is 32 bit
@@ -72,7 +76,8 @@ impl Bus {
             source &= !3;
 
             for i in 0..wordCount {
-                self.write32(dest, self.read32(source));
+                let val = self.read32(source);
+                self.write32(dest, val);
                 dest += DMAOffsets[destAddrControl] as u32;
                 source += DMAOffsets[srcAddrControl] as u32;
             }
@@ -83,12 +88,15 @@ impl Bus {
             source &= !1;
 
             for i in 0..wordCount {
-                self.write16(dest, self.read16(source));
+                let val = self.read16(source);
+                self.write16(dest, val);
                 dest += (DMAOffsets[destAddrControl] >> 1) as u32;
                 source += (DMAOffsets[srcAddrControl] >> 1) as u32;
             }
         }
 
+        self.addIdleCycles(2); // DMAs take 2 internal cycles to start up
+
         if controlReg.shouldFireIRQ() { // Request IRQ upon end of word count 
             self.dma_irq_requests |= (1 << 8 << channel);
         }
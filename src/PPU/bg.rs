@@ -1,9 +1,9 @@
-use ppu::PPU;
+use ppu::{PPU, DIRECT_COLOR};
 use crate::PPU::*;
 use crate::sign_extend_32;
-use crate::helpers::get8BitColor;
 
 const AFFINE_BG_SIZES: [u32; 4] = [128, 256, 512, 1024]; // Affine BGs are always square bois
+const BITMAP_FRAME_SIZE: u32 = 0xA000; // Offset of the 2nd frame in modes 4 and 5
 
 impl PPU {
     
@@ -34,22 +34,65 @@ impl PPU {
         }
     }
 
-    pub fn renderMode3(&mut self) { // Mode 3 stub
-        let mut mapDataBase = self.vcount as u32 * 240 * 2;
-        let mut bufferIndex = self.vcount as usize * 240 * 4;
+    pub fn renderMode2(&mut self) { // BG2 and BG3 are both affine, BG0 and BG1 aren't available
+        self.fetchSprites();
+
+        for prio in 0..4 {
+            self.renderSprites(prio);
+            if self.bg_controls[2].getPriority() == prio {self.renderAffineBG(2)}
+            if self.bg_controls[3].getPriority() == prio {self.renderAffineBG(3)}
+        }
+    }
+
+    pub fn renderMode3(&mut self) { // 240x160 direct colour bitmap
+        self.renderBitmapMode(240, 160, true, 0);
+    }
+
+    pub fn renderMode4(&mut self) { // 240x160 paletted bitmap, 2 frames
+        self.renderBitmapMode(240, 160, false, self.dispcnt.getFrameSelect() as u32 * BITMAP_FRAME_SIZE);
+    }
+
+    pub fn renderMode5(&mut self) { // 160x128 direct colour bitmap, 2 frames
+        self.renderBitmapMode(160, 128, true, self.dispcnt.getFrameSelect() as u32 * BITMAP_FRAME_SIZE);
+    }
+
+    fn renderBitmapMode(&mut self, width: i32, height: i32, is16bpp: bool, frameBase: u32) {
+        self.fetchSprites();
+
+        for prio in 0..4 {
+            self.renderSprites(prio);
+            if self.bg_controls[2].getPriority() == prio {self.renderBitmapBG(width, height, is16bpp, frameBase)}
+        }
+    }
+
+    // BG2 in modes 3-5. Bitmaps use the BG2 affine transform too, and never wrap around
+    fn renderBitmapBG(&mut self, width: i32, height: i32, is16bpp: bool, frameBase: u32) {
+        if self.dispcnt.getRaw() & (1 << 10) == 0 { // If BG2 is disabled, exit
+            return;
+        }
 
-        for i in 0..240 {
-            let pixel = self.readVRAM16(mapDataBase); 
-            let red = get8BitColor((pixel & 0x1F) as u8); //R
-            let green = get8BitColor(((pixel >> 5) & 0x1F) as u8); //G
-            let blue = get8BitColor(((pixel >> 10) & 0x1F) as u8); //B
+        let pa = self.aff_bg_pa[0].getRaw() as i16 as i32;
+        let pc = self.aff_bg_pc[0].getRaw() as i16 as i32;
+        let dx = self.aff_bg_internal_x[0];
+        let dy = self.aff_bg_internal_y[0];
 
-            self.pixels[bufferIndex] = red;
-            self.pixels[bufferIndex+1] = green;
-            self.pixels[bufferIndex+2] = blue;
+        for x in 0..240 {
+            if self.currentLine[x] != 0 { // If the pixel has already been drawn over by a higher prio layer, skip it
+                continue
+            }
+
+            let x_coord = (dx + pa * (x as i32)) >> 8;
+            let y_coord = (dy + pc * (x as i32)) >> 8;
+            if x_coord < 0 || y_coord < 0 || x_coord >= width || y_coord >= height {
+                continue;
+            }
 
-            mapDataBase += 2;
-            bufferIndex += 4;
+            let index = (y_coord * width + x_coord) as u32;
+            if is16bpp {
+                self.currentLine[x] = DIRECT_COLOR | (self.readVRAM16(frameBase + index * 2) & 0x7FFF);
+            } else {
+                self.currentLine[x] = self.VRAM[(frameBase + index) as usize] as u16; // Palette index 0 is transparent, same as tiled BGs
+            }
         }
     }
 
@@ -147,20 +190,16 @@ impl PPU {
 
         let bgcnt = &self.bg_controls[bg_num];
         let tileDataBase = (bgcnt.getTileDataBase() as u32) << 14;
-        let mut mapDataBase = (bgcnt.getMapDataBase() as u32) << 11;
-
-        assert!(bgcnt.getDisplayAreaOverflow() == 0);
-        let pa = self.aff_bg_pa[bg_num - 2].getRaw() as i32;
-        let pc = self.aff_bg_pc[bg_num - 2].getRaw() as i32;
+        let mapDataBase = (bgcnt.getMapDataBase() as u32) << 11;
+        let wraparound = bgcnt.getDisplayAreaOverflow() == 1; // If not set, pixels outside of the map are transparent
 
-        let dx = self.aff_bg_dx[bg_num - 2].getRaw() as i16 as i32;
-        let dy = self.aff_bg_dy[bg_num - 2].getRaw() as i16 as i32;
+        let pa = self.aff_bg_pa[bg_num - 2].getRaw() as i16 as i32;
+        let pc = self.aff_bg_pc[bg_num - 2].getRaw() as i16 as i32;
+        let dx = self.aff_bg_internal_x[bg_num - 2];
+        let dy = self.aff_bg_internal_y[bg_num - 2];
 
-        let size = AFFINE_BG_SIZES[bgcnt.getSize() as usize]; // affine BGs are always square
-
-        // non affine code
-
-        let y = self.vcount;
+        let size = AFFINE_BG_SIZES[bgcnt.getSize() as usize] as i32; // affine BGs are always square
+        let tilesPerRow = (size >> 3) as u32; // 16, 32, 64 or 128 tiles
 
         for x in 0..240 {
             
@@ -168,45 +207,33 @@ impl PPU {
                 continue
             }
 
-            let x_coord = ((dx + pa * (x as i32)) >> 8) as u32;
-            let y_coord = ((dy + pc * (x as i32)) >> 8) as u32;
-            let mapStart = mapDataBase + ((y_coord as u32 >> 3) & 31) * 64; // & 31 => wrap around the 32x32 tile map (TODO: add big map support)
+            let mut x_coord = (dx + pa * (x as i32)) >> 8;
+            let mut y_coord = (dy + pc * (x as i32)) >> 8;
 
-            if x_coord > size || y_coord > size {
+            if wraparound {
+                x_coord &= size - 1;
+                y_coord &= size - 1;
+            } else if x_coord < 0 || y_coord < 0 || x_coord >= size || y_coord >= size {
                 continue;
             }
 
-            let mut tile_x = x_coord & 7;
-            let mut tile_y = y_coord as u16 & 7;
-            let mapAddr = mapStart + (((x_coord >> 3) & 31) << 1);
+            let (x_coord, y_coord) = (x_coord as u32, y_coord as u32);
+            let tile_x = x_coord & 7;
+            let tile_y = y_coord & 7;
+            let mapAddr = mapDataBase + (y_coord >> 3) * tilesPerRow + (x_coord >> 3);
 
-
-            let mapEntry = self.readVRAM16(mapAddr);
-            let tileNum = (mapEntry & 0x3FF) as u32;
-
-            tile_x ^= (sign_extend_32!(mapEntry, 11) >> 12) & 7; // fast-ish flipping impl. TODO: Check if it's actually faster
-            tile_y ^= (sign_extend_32!(mapEntry, 12) >> 13) as u16 & 7;
+            // Affine map entries are just an 8-bit tile number. No flipping or palette bits, tiles are always 8bpp
+            let tileNum = self.VRAM[mapAddr as usize] as u32;
 
             let mut tileAddr = tileDataBase;
 
             tileAddr += tileNum * 64;
-            tileAddr += tile_y as u32 * 8;
+            tileAddr += tile_y * 8;
             tileAddr += tile_x;
                 
             let pixel = self.VRAM[tileAddr as usize];
             
             self.currentLine[x as usize] = pixel as u16;
         }
-        //self.renderNonAffineBG(bg_num);
-    }
-
-    // simple stub for AW
-    pub fn renderMode4 (&mut self) {
-        let mut vramIndex = (self.vcount * 240) as usize;
-
-        for x in 0..240 {
-            self.currentLine[x] = self.VRAM[vramIndex] as u16;
-            vramIndex += 1;
-        }
     }
 }
\ No newline at end of file
@@ -6,6 +6,7 @@ const HBLANK_MODE_CYCLES: u32 = 272;
 const CYCLES_PER_LINE: u32 = 1232;
 pub const WIDTH: usize = 240;
 pub const HEIGHT: usize = 160;
+pub const DIRECT_COLOR: u16 = 0x8000; // Set in currentLine for BGR555 pixels from modes 3 and 5, which don't go through the palette
 
 pub struct PPU {
     pub dispcnt: DISPCNT,
@@ -21,6 +22,8 @@ pub struct PPU {
     pub aff_bg_pd: [RotationAndScalingParam; 2], // BG Rotation/Scaling Parameter D
     pub aff_bg_dx: [BGRefPoint; 2], // BG2 Reference Point X-Coordinate
     pub aff_bg_dy: [BGRefPoint; 2], // BG2 Reference Point Y-Coordinate
+    pub aff_bg_internal_x: [i32; 2], // Internal copies of the reference points, reloaded on VBlank and on writes and advanced every line
+    pub aff_bg_internal_y: [i32; 2],
 
     pub bldy: u32,
     pub vcount: u16, // Only lower 8 bits are used on the GBA
@@ -33,7 +36,7 @@ pub struct PPU {
     pub paletteCache: [[u8; 3]; 512],
     pub sprites: Vec<Sprite>,
     pub interruptFlags: u16,
-    pub currentLine: [u16; WIDTH] // Palette indices for each pixel of the line. Used for multiple BG rendering
+    pub currentLine: [u16; WIDTH] // Palette indices (or DIRECT_COLOR | BGR555) for each pixel of the line. Used for multiple BG rendering
 }
 
 impl PPU {
@@ -45,13 +48,15 @@ impl PPU {
             bg_hofs: [BGOFS(0), BGOFS(0), BGOFS(0), BGOFS(0)],
             bg_vofs: [BGOFS(0), BGOFS(0), BGOFS(0), BGOFS(0)],
 
-            aff_bg_pa: [RotationAndScalingParam(0), RotationAndScalingParam(0)], // BG Rotation/Scaling Parameter A
+            aff_bg_pa: [RotationAndScalingParam(0x100), RotationAndScalingParam(0x100)], // BG Rotation/Scaling Parameter A. PA and PD reset to 1.0, so bitmap modes work without touching them
             aff_bg_pb: [RotationAndScalingParam(0), RotationAndScalingParam(0)], // BG Rotation/Scaling Parameter B
             aff_bg_pc: [RotationAndScalingParam(0), RotationAndScalingParam(0)], // BG Rotation/Scaling Parameter C
-            aff_bg_pd: [RotationAndScalingParam(0), RotationAndScalingParam(0)], // BG Rotation/Scaling Parameter D
+            aff_bg_pd: [RotationAndScalingParam(0x100), RotationAndScalingParam(0x100)], // BG Rotation/Scaling Parameter D
 
             aff_bg_dx: [BGRefPoint(0), BGRefPoint(0)], // BG Reference Point X-Coordinate
             aff_bg_dy: [BGRefPoint(0), BGRefPoint(0)], // BG Reference Point Y-Coordinate
+            aff_bg_internal_x: [0, 0],
+            aff_bg_internal_y: [0, 0],
             
             bldy: 0,
             vcount: 0,
@@ -87,14 +92,7 @@ impl PPU {
     // cache the newly written BGR555 palette value as an RGB888 value
     #[inline(always)]
     pub fn updatePalette (&mut self, palNum: u16) {
-        let palette = self.readPalette16(palNum);
-        let red = get8BitColor((palette & 0x1F) as u8);          // red
-        let green = get8BitColor(((palette >> 5) & 0x1F) as u8); // green
-        let blue = get8BitColor(((palette >> 10) & 0x1F) as u8); // blue
-
-        self.paletteCache[palNum as usize][0] = red;
-        self.paletteCache[palNum as usize][1] = green;
-        self.paletteCache[palNum as usize][2] = blue;
+        self.paletteCache[palNum as usize] = bgr555ToRGB888(self.readPalette16(palNum));
     }
 
     pub fn renderScanline(&mut self) {
@@ -105,20 +103,24 @@ impl PPU {
 
         match self.dispcnt.getMode() {
             0 => self.renderMode0(),
-            1 => self.renderMode0(),
-            //1 => self.renderMode1(),
-            3 => { self.renderMode3(); return; }, // Mode 3 isn't palette based, so we gotta early exit
+            1 => self.renderMode1(),
+            2 => self.renderMode2(),
+            3 => self.renderMode3(),
             4 => self.renderMode4(),
+            5 => self.renderMode5(),
             _ => panic!("Unimplemented BG mode {}", self.dispcnt.getMode())
         }
 
         let mut bufferIndex = self.vcount as usize * WIDTH * 4; // Get the framebuffer position of the current line
 
         for i in 0..WIDTH { // Copy the rendered line to the fb
+            let pixel = self.currentLine[i];
+            let color = if (pixel & DIRECT_COLOR) != 0 { bgr555ToRGB888(pixel) } else { self.paletteCache[pixel as usize] };
+
             // store rgb888 color to buffer
-            self.pixels[bufferIndex] = self.paletteCache[self.currentLine[i] as usize][0];
-            self.pixels[bufferIndex+1] = self.paletteCache[self.currentLine[i] as usize][1];
-            self.pixels[bufferIndex+2] = self.paletteCache[self.currentLine[i] as usize][2];
+            self.pixels[bufferIndex] = color[0];
+            self.pixels[bufferIndex+1] = color[1];
+            self.pixels[bufferIndex+2] = color[2];
             bufferIndex += 4;
         }
     }
@@ -142,11 +144,49 @@ impl PPU {
         }
     }
 
+    // Write to BG2X/BG2Y/BG3X/BG3Y, or a 16-bit half of one. Writing to a reference point reloads its internal register
+    pub fn writeRefPoint(&mut self, address: u32, val: u32, mask: u32) {
+        let bg = ((address >> 4) & 1) as usize; // 0x4000028-0x400002F => BG2, 0x4000038-0x400003F => BG3
+        let shift = (address & 2) * 8; // Upper half of the register
+        let isY = (address & 4) != 0;
+
+        let reg = if isY { &mut self.aff_bg_dy[bg] } else { &mut self.aff_bg_dx[bg] };
+        reg.setRaw((reg.getRaw() & !(mask << shift)) | ((val & mask) << shift));
+        let internal = refPointValue(*reg);
+
+        if isY {
+            self.aff_bg_internal_y[bg] = internal;
+        } else {
+            self.aff_bg_internal_x[bg] = internal;
+        }
+    }
+
+    // Reload the internal reference points at the start of VBlank
+    pub fn latchAffineRegs(&mut self) {
+        for bg in 0..2 {
+            self.aff_bg_internal_x[bg] = refPointValue(self.aff_bg_dx[bg]);
+            self.aff_bg_internal_y[bg] = refPointValue(self.aff_bg_dy[bg]);
+        }
+    }
+
+    // Move the internal reference points down one line
     pub fn incrementAffineRegs(&mut self) {
-        // todo: Make aff regs latch
-        self.aff_bg_dx[0].setRaw(self.aff_bg_dx[0].getRaw() + self.aff_bg_pb[0].getRaw() as u32); // inc BG2X by BG2PB
-        self.aff_bg_dx[1].setRaw(self.aff_bg_dx[1].getRaw() + self.aff_bg_pb[1].getRaw() as u32);
-        self.aff_bg_dy[0].setRaw(self.aff_bg_dy[0].getRaw() + self.aff_bg_pd[0].getRaw() as u32);
-        self.aff_bg_dy[1].setRaw(self.aff_bg_dy[1].getRaw() + self.aff_bg_pd[1].getRaw() as u32);
+        for bg in 0..2 {
+            self.aff_bg_internal_x[bg] += self.aff_bg_pb[bg].getRaw() as i16 as i32; // inc BG2X by BG2PB
+            self.aff_bg_internal_y[bg] += self.aff_bg_pd[bg].getRaw() as i16 as i32; // inc BG2Y by BG2PD
+        }
     }
-}
\ No newline at end of file
+}
+
+fn bgr555ToRGB888(color: u16) -> [u8; 3] {
+    [
+        get8BitColor((color & 0x1F) as u8),         // red
+        get8BitColor(((color >> 5) & 0x1F) as u8),  // green
+        get8BitColor(((color >> 10) & 0x1F) as u8)  // blue
+    ]
+}
+
+// Sign extend a 28-bit (20.8 fixed point) reference point
+fn refPointValue(reg: BGRefPoint) -> i32 {
+    ((reg.getRaw() << 4) as i32) >> 4
+}
@@ -1,7 +1,6 @@
 use ppu::PPU;
 use crate::PPU::*;
 use crate::isBitSet;
-use crate::sign_extend_16;
 
 const OAM_MAX: usize = 0x3FF;
 const SPRITE_SIZES: [[[u16; 2]; 4]; 4] = [ // Look up table of all sprite x/y sizes
@@ -26,7 +25,9 @@ pub struct Sprite {
 
     pub shape: u16, // 0: square. 1: horizontal. 2: vertical
     pub size: u16,
-    pub doubleSize: bool
+    pub isAffine: bool, // Whether the sprite is rotated/scaled
+    pub affineGroup: u16, // Which of the 32 rotation/scaling parameter groups in OAM the sprite uses
+    pub doubleSize: bool // Affine sprites only. Doubles the area the sprite is drawn in, so it doesn't get clipped when rotated
 }
 
 impl Sprite {
@@ -40,11 +41,13 @@ impl Sprite {
             palNum: ((attr2 >> 12) & 0xF) as u8,
 
             is8bpp: isBitSet!(attr0, 13),
-            h_flip: (attr1 >> 12) & 1,
+            h_flip: (attr1 >> 12) & 1, // Flip bits are replaced by the parameter group for affine sprites
             v_flip: (attr1 >> 13) & 1,
 
             shape: (attr0 >> 14) & 3,
             size: (attr1 >> 14) & 3,
+            isAffine: isBitSet!(attr0, 8),
+            affineGroup: (attr1 >> 9) & 0x1F,
             doubleSize: isBitSet!(attr0, 9)
         }
     }
@@ -63,22 +66,20 @@ impl PPU {
                 continue
             } 
 
-            let mut x_coord = (attr1 & 0x1FF);
-            let mut y_coord = (attr0 & 0xFF);
+            let mut x_coord = attr1 & 0x1FF;
+            let y_coord = attr0 & 0xFF;
 
             let size = (attr1 >> 14) & 3;
             let shape = (attr0 >> 14) & 3;
 
-            if x_coord >= 240 {x_coord -= 512}
-            if y_coord >= 160 {y_coord -= 256}
-            
-            //x_coord &= 0x1FF;
-            y_coord &= 255;
+            if x_coord >= 240 {x_coord = x_coord.wrapping_sub(512)}
 
-            let SPRITE_Y = SPRITE_SIZES[size as usize][shape as usize][1];  // Todo: Implement different sprite shapes and sizes
-            let sprite_end = (SPRITE_Y + y_coord) & 0xFF;
+            let mut SPRITE_Y = SPRITE_SIZES[size as usize][shape as usize][1];
+            if isBitSet!(attr0, 8) && isBitSet!(attr0, 9) { // Double size affine sprite
+                SPRITE_Y *= 2;
+            }
 
-            if (y_coord <= self.vcount && sprite_end > self.vcount) || (sprite_end < y_coord && self.vcount < sprite_end) {
+            if (self.vcount.wrapping_sub(y_coord) & 0xFF) < SPRITE_Y { // Sprites that go past the bottom of the screen wrap around to the top
                 self.sprites.push(Sprite::new(attr0, attr1, self.readOAM16(i + 4), x_coord));
             } 
         }
@@ -86,88 +87,91 @@ impl PPU {
         //self.sprites.sort(); 
     }
 
-    pub fn renderSprites(&mut self, prio: u16) { // TODO: Account for OBJ priority
+    pub fn renderSprites(&mut self, prio: u16) { // Only draws sprites with this priority, before the BGs of the same priority
+        // In bitmap modes the bitmap takes up VRAM up to 0x14000, so only the upper half of OBJ VRAM holds sprite tiles.
+        // Tile numbers still count from 0x10000, and tiles in the lower half aren't displayed
+        let objVRAMStart = if self.dispcnt.getMode() >= 3 { 0x14000 } else { 0x10000 };
+
         for sprite in &self.sprites {
             if sprite.priority != prio {continue;}
 
-            let SPRITE_X = SPRITE_SIZES[sprite.size as usize][sprite.shape as usize][0];
-            let SPRITE_Y = SPRITE_SIZES[sprite.size as usize][sprite.shape as usize][1];
-            let linesSinceOBJStart = (self.vcount as u32 - sprite.y_coord as u32) & (SPRITE_Y as u32-1);
-            
-            //assert!(!sprite.doubleSize); 
-            //if sprite.doubleSize {
-            //    SPRITE_X *= 2;
-            //    SPRITE_Y *= 2;
-            //  }
+            let SPRITE_X = SPRITE_SIZES[sprite.size as usize][sprite.shape as usize][0] as i32;
+            let SPRITE_Y = SPRITE_SIZES[sprite.size as usize][sprite.shape as usize][1] as i32;
+            let (boxWidth, boxHeight) = if sprite.isAffine && sprite.doubleSize { (SPRITE_X * 2, SPRITE_Y * 2) } else { (SPRITE_X, SPRITE_Y) };
+            let linesSinceOBJStart = (self.vcount as i32 - sprite.y_coord as i32) & 0xFF;
+
+            let (pa, pb, pc, pd) = if sprite.isAffine { self.readOBJAffineParams(sprite.affineGroup) } else { (0x100, 0, 0, 0x100) };
                 
-            for i in 0..SPRITE_X {
-                let x = sprite.x_coord + i;
-                if x >= 240 {continue}
+            for i in 0..boxWidth {
+                let x = sprite.x_coord as i16 as i32 + i;
+                if !(0..240).contains(&x) {continue}
                 if self.currentLine[x as usize] != 0 {continue}
 
-                let mut tile_x = i;
-                let mut tile_y = linesSinceOBJStart as u16;
-
-                tile_x ^= (((sprite.h_flip as i16) << 15 >> 15) as u16) & (SPRITE_X-1); // fast-ish, branchless mirroring
-                tile_y ^= (((sprite.v_flip as i16) << 15 >> 15) as u16) & (SPRITE_Y-1);
+                let (tile_x, tile_y) = if sprite.isAffine { // Rotate/scale around the centre of the sprite
+                    let dx = i - boxWidth / 2;
+                    let dy = linesSinceOBJStart - boxHeight / 2;
+                    (((pa * dx + pb * dy) >> 8) + SPRITE_X / 2, ((pc * dx + pd * dy) >> 8) + SPRITE_Y / 2)
+                } else {
+                    (if sprite.h_flip == 1 { SPRITE_X - 1 - i } else { i }, if sprite.v_flip == 1 { SPRITE_Y - 1 - linesSinceOBJStart } else { linesSinceOBJStart })
+                };
 
-                let mut tile_addr = 0x10000; // TODO: Add sprite support for modes 3-5. These modes DO NOT use 0x10000 as the base, but 0x14000
-
-                let mut pixel: u8;
-                
-                if sprite.is8bpp {
-                    tile_addr += (sprite.tile_num >> 1) as u32 * 64;
-                    //tile_addr += ((x as u32) >> 3) * 64;
-                    //tile_addr += ((self.vcount as u32 & 31) >> 3) * 0x800;
-                    //panic!("We don't have 8bpp sprites ree");
-                    tile_addr += (tile_y as u32 & 7) * 8;
-                    tile_addr += tile_x as u32 & 7;
-                    tile_addr += ((tile_x as u32) >> 3) * 64;
-
-                    if self.dispcnt.OBJ1DMapping() { 
-                        tile_addr += (tile_y as u32 / 8) * 64 * (SPRITE_X as u32 >> 3);
-                        //panic!("8bpp 1D sprite")
-                    }
-
-                    else {
-                        panic!("8bpp 2D sprite")
-                    }
-                    
-                    pixel = self.VRAM[tile_addr as usize]
-                }
-                
-                else {
-                    tile_addr += sprite.tile_num as u32 * 32;
-                    tile_addr += (tile_y & 7) as u32 * 4;
-                    tile_addr += ((tile_x as u32 & 7) >> 1);
-                    
-                    tile_addr += ((tile_x as u32) >> 3) * 32;
-                    
-                    if self.dispcnt.OBJ1DMapping() { 
-                        tile_addr += (tile_y as u32 / 8) * 32 * (SPRITE_X as u32 >> 3);
-                    }
-
-                    else {
-                        tile_addr += ((tile_y as u32) >> 3) * 32 * 0x20; // TODO: Make sure this is correct
-                        //println!("Non horizontal OBJ mapping! (Broken?)")
-                    }
-
-                    let twoDots = self.VRAM[tile_addr as usize];
-                    pixel = (twoDots >> ((tile_x & 1) << 2)) & 0xF;
-
-                    if pixel != 0 {
-                        pixel += sprite.palNum * 16;
-                    }
+                if tile_x < 0 || tile_y < 0 || tile_x >= SPRITE_X || tile_y >= SPRITE_Y { // Parts of the box outside of the transformed sprite are transparent
+                    continue;
                 }
 
+                let pixel = self.spritePixel(sprite, tile_x as u32, tile_y as u32, SPRITE_X as u32, objVRAMStart);
                 if pixel != 0 {
                     self.currentLine[x as usize] = pixel as u16 + 256;
                 }
+            }
+        }
+    }
 
-                else {
-                    self.currentLine[x as usize] = 0;
-                }
+    // Rotation/scaling parameters are interleaved with the sprite attributes. Each group's PA-PD are spread across 4 OAM entries
+    fn readOBJAffineParams(&self, group: u16) -> (i32, i32, i32, i32) {
+        let base = group as usize * 32;
+        (
+            self.readOAM16(base + 6) as i16 as i32,
+            self.readOAM16(base + 14) as i16 as i32,
+            self.readOAM16(base + 22) as i16 as i32,
+            self.readOAM16(base + 30) as i16 as i32
+        )
+    }
+
+    // Get the palette index of a pixel in a sprite (without the +256 for OBJ palettes). 0 means transparent
+    fn spritePixel(&self, sprite: &Sprite, tile_x: u32, tile_y: u32, SPRITE_X: u32, objVRAMStart: u32) -> u8 {
+        let tileSize = if sprite.is8bpp { 2 } else { 1 }; // Tile numbers are in units of 32 bytes, so 8bpp tiles take up 2 of them
+        let mut tileNum = sprite.tile_num as u32;
+
+        if self.dispcnt.OBJ1DMapping() { // 1D: The sprite's tiles are stored one after another
+            tileNum += (tile_y >> 3) * (SPRITE_X >> 3) * tileSize;
+        }
+
+        else { // 2D: OBJ VRAM is a 32x32 tile matrix, so each row of the sprite is 32 tiles further on
+            if sprite.is8bpp {
+                tileNum &= !1; // Bit 0 of the tile number is ignored for 8bpp sprites in 2D mode
             }
+
+            tileNum += (tile_y >> 3) * 32;
+        }
+
+        tileNum = (tileNum + (tile_x >> 3) * tileSize) & 0x3FF;
+        if 0x10000 + tileNum * 32 < objVRAMStart { // Tiles 0-511 are bitmap data in modes 3-5
+            return 0;
+        }
+
+        let offset = if sprite.is8bpp { (tile_y & 7) * 8 + (tile_x & 7) } else { (tile_y & 7) * 4 + ((tile_x & 7) >> 1) };
+        let tile_addr = 0x10000 + ((tileNum * 32 + offset) & 0x7FFF); // Wrap around the 32KB of OBJ VRAM
+
+        if sprite.is8bpp {
+            self.VRAM[tile_addr as usize]
+        }
+        
+        else {
+            let twoDots = self.VRAM[tile_addr as usize];
+            let pixel = (twoDots >> ((tile_x & 1) << 2)) & 0xF;
+
+            if pixel != 0 { pixel + sprite.palNum * 16 } else { 0 }
         }
     }
-}
\ No newline at end of file
+}
@@ -44,6 +44,11 @@ impl CPU {
     }
 
     pub fn Thumb_handleSWI (&mut self, bus: &mut Bus, instruction: u32) {
+        if bus.isHLEBios() {
+            self.HLE_handleSWI(bus, instruction & 0xFF);
+            return;
+        }
+
         let lr = self.gprs[15] - 2;
         let cpsr = self.cpsr.getRaw();
         self.changeMode(0x13); // switch to SVC mode
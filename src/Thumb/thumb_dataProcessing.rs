@@ -83,6 +83,12 @@ impl CPU {
         let mut rs = self.gprs[rsIndex as usize];
         let rd = self.gprs[rdIndex];
 
+        match opcode {
+            2 | 3 | 4 | 7 => bus.addIdleCycles(1), // Shifting by a register takes an extra internal cycle
+            13 => bus.addIdleCycles(Self::getMultiplyCycles(rd, true)),
+            _ => {}
+        }
+
         match opcode {
             0 => self.gprs[rdIndex] = self._AND(rd, rs, true),
             1 => self.gprs[rdIndex] = self._EOR(rd, rs, true),
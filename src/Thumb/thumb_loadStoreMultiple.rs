@@ -35,6 +35,8 @@ impl CPU {
             self.setGPR(15, bus.read32(self.gprs[13]), bus);
             self.gprs[13] += 4;
         }
+
+        bus.addIdleCycles(1); // 1 internal cycle to write back the last loaded register
     }
 
     pub fn Thumb_handleSTMIA (&mut self, bus: &mut Bus, instruction: u32) {
@@ -69,5 +71,6 @@ impl CPU {
         }
 
         self.gprs[rbIndex as usize] = sp;
+        bus.addIdleCycles(1); // 1 internal cycle to write back the last loaded register
     }
 }
\ No newline at end of file
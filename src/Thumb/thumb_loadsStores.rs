@@ -11,6 +11,7 @@ impl CPU {
         let rdIndex = (instruction >> 8) & 0x7;
 
         self.gprs[rdIndex as usize] = bus.read32(sp + imm);
+        bus.addIdleCycles(1); // 1I for the load
     }
 
     pub fn Thumb_handleSPRelativeStore (&mut self, bus: &mut Bus, instruction: u32) {
@@ -27,6 +28,7 @@ impl CPU {
         let addr = (self.gprs[15] & !2) + imm;
 
         let mut val = bus.read32(addr & !3);
+        bus.addIdleCycles(1); // 1I for the load
         val = self.ROR(val, 8 * (addr & 0x3), false);
 
         self.gprs[rdIndex as usize] = val;
@@ -46,6 +48,7 @@ impl CPU {
         let offset = ((instruction >> 6) & 0x1F) << 1;
         let address = rb + offset;
         let mut val = (bus.read16(address & !1)) as u32; // handle mem alignment
+        bus.addIdleCycles(1); // 1I for the load
         val = self.ROR(val, 8 * (address & 1), false);
 
         self.gprs[rdIndex as usize] = val;
@@ -58,6 +61,7 @@ impl CPU {
         let address = rb + offset;
 
         let mut val = bus.read32((address) & !3);
+        bus.addIdleCycles(1); // 1I for the load
         val = self.ROR(val, 8 * (address & 3), false);
 
         self.gprs[rdIndex as usize] = val;
@@ -77,6 +81,7 @@ impl CPU {
         let offset = (instruction >> 6) & 0x1F;
 
         self.gprs[rdIndex as usize] = bus.read8(rb + offset) as u32;
+        bus.addIdleCycles(1); // 1I for the load
     }
 
     pub fn Thumb_handleStoreByteWithImm (&mut self, bus: &mut Bus, instruction: u32) {
@@ -94,6 +99,7 @@ impl CPU {
         let addr = ro + rb;
 
         let mut val = bus.read32(addr & !3);
+        bus.addIdleCycles(1); // 1I for the load
         val = self.ROR(val, 8 * (addr & 3), false);
         self.gprs[rdIndex as usize] = val;
     }
@@ -114,6 +120,7 @@ impl CPU {
         let addr = ro + rb;
 
         let val = bus.read8(addr);
+        bus.addIdleCycles(1); // 1I for the load
         self.gprs[rdIndex as usize] = val as u32;
     }
 
@@ -141,6 +148,7 @@ impl CPU {
         let ro = self.gprs[(instruction as usize >> 6) & 7];
         let addr = ro + rb;
         let mut val = bus.read16(addr & !1) as u32; //  Handle misaligned addresses 
+        bus.addIdleCycles(1); // 1I for the load
         val = self.ROR(val, 8 * (addr & 1), false);
 
         self.gprs[rdIndex as usize] = val;
@@ -153,6 +161,7 @@ impl CPU {
         let addr = ro + rb;
 
         let mut val = bus.read8(addr) as u32;
+        bus.addIdleCycles(1); // 1I for the load
         val = sign_extend_32!(val, 8); // Sign extend the value to 32 bits from 8 bits
 
         self.gprs[rdIndex as usize] = val;
@@ -165,6 +174,7 @@ impl CPU {
         let addr = ro + rb;
 
         let mut val = bus.read16(addr) as u32;
+        bus.addIdleCycles(1); // 1I for the load
         val = sign_extend_32!(val, 16); // Sign extend the value to 32 bits from 16 bits
 
         self.gprs[rdIndex as usize] = val;
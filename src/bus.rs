@@ -1,5 +1,5 @@
 use ppu::PPU;
-use std::io::*;
+use std::io::Write;
 
 use crate::mem::*;
 use crate::PPU::ppu;
@@ -7,48 +7,97 @@ use crate::DMA::DMAChannel;
 use crate::joypad::Joypad;
 use crate::timers::Timers;
 use crate::scheduler::*;
+use crate::waitstates::Waitstates;
+use crate::prefetch::Prefetcher;
+use crate::cartridge::Backup;
+use crate::gpio::Gpio;
+use crate::tilt::TiltSensor;
+use crate::cheats::Cheats;
 
 pub struct Bus {
-    mem: Memory,
+    pub mem: Memory,
     pub ppu: PPU,
     pub timers: Timers,
     pub joypad: Joypad,
     pub dmaChannels: [DMAChannel; 4],
     pub scheduler: Scheduler,
+    pub waitstates: Waitstates,
+    pub prefetcher: Prefetcher,
+    pub gpio: Gpio,
+    pub tilt: Option<TiltSensor>,
+    pub cheats: Cheats,
+    pub isCodeFetch: bool, // Whether the current access is an opcode fetch, for the prefetch buffer
 
     // some MMIO registers that don't really fit in the peripheral structs
     // interrupt registers
     pub ime: bool,  // interrupt master enable register
     pub ie: u16,    // interrupt enable register
     pub dma_irq_requests: u16, 
+    pub gamepak_irq_request: bool,
 
     // stubbed MMIO registers that I need for the BIOS but haven't properly implemented yet
     soundbiasStub: u32,
-    waitcnt: u16,
+    rcntStub: u16,
+    pub postflg: u8, // Set by the BIOS after the first boot
+    pub waitcnt: u16,
     pub halted: bool
 } 
 
 impl Bus {
-    pub fn new(romPath: String) -> Bus {
+    pub fn new(mem: Memory) -> Bus {
+        let gpio = Gpio::new(&mem.ROM[..mem.romSize], &mem.header);
+        let tilt = if TiltSensor::isPresent(&mem.header) { Some(TiltSensor::new()) } else { None };
+
         Bus {
-            mem: Memory::new(romPath),
+            mem,
             ppu: PPU::new(),
             timers: Timers::new(),
             joypad: Joypad::new(),
             dmaChannels: [DMAChannel::new(), DMAChannel::new(), DMAChannel::new(), DMAChannel::new()],
             scheduler: Scheduler::new(),
+            waitstates: Waitstates::new(),
+            prefetcher: Prefetcher::new(),
+            gpio,
+            tilt,
+            cheats: Cheats::new(),
+            isCodeFetch: false,
 
             ime: false,
             ie: 0,
             dma_irq_requests: 0, 
+            gamepak_irq_request: false,
             soundbiasStub: 0,
+            rcntStub: 0,
+            postflg: 0,
             waitcnt: 0,
             halted: false
         }
     }
 
+    pub fn isHLEBios(&self) -> bool {
+        self.mem.isHLEBios
+    }
+
+    // EEPROM is mapped to the top of the ROM area. That's all of 0x0D000000-0x0DFFFFFF for ROMs up to 16MB,
+    // or 0x0DFFFF00-0x0DFFFFFF for bigger ones
+    pub fn isEEPROMAccess(&self, address: u32) -> bool {
+        matches!(self.mem.backup, Backup::EEPROM(_)) && (address >> 24) == 0xD && (self.mem.romSize <= 16 * 1024 * 1024 || address >= 0x0DFFFF00)
+    }
+
+    pub fn isTiltAccess(&self, address: u32) -> bool {
+        self.tilt.is_some() && TiltSensor::isTiltAddress(address)
+    }
+
+    // Set up the IO registers the way the BIOS leaves them before jumping to the ROM
+    pub fn skipBIOS(&mut self) {
+        self.postflg = 1;
+        self.soundbiasStub = 0x200;
+        self.rcntStub = 0x8000;
+    }
+
     #[inline(always)]
-    pub fn read8 (&self, address: u32) -> u8 {
+    pub fn read8 (&mut self, address: u32) -> u8 {
+        self.addAccessCycles(address, 1);
         match address >> 24 {
             0 => self.mem.BIOS[address as usize & 0x3FFF], // TODO: Don't mirror BIOS.
             1 => {println!("Read from unused mem. Todo: remove this msg"); return 0;}
@@ -65,19 +114,17 @@ impl Bus {
             8 | 9 => self.mem.ROM[(address - 0x8000000) as usize],
             0xA | 0xB => self.mem.ROM[(address - 0xA000000) as usize],
             0xC | 0xD => self.mem.ROM[(address - 0xC000000) as usize],
-            0xE | 0xF => { // TODO: Add support for different backup media
-                if address == 0xE000000 {return 0xC2_u8}; // (FLASH STUB)
-                if address == 0xE000001 {return 0x9_u8}; // (FLASH STUB)
-                self.mem.SRAM[(address & 0xFFFF) as usize]
-            }
+            0xE if self.isTiltAccess(address) => self.tilt.as_ref().unwrap().read(address),
+            0xE | 0xF => self.mem.backup.read8(address),
 
             _ => todo!("Unimplemented 8-bit read at address {:08X}", address)
         }
     }
 
     #[inline(always)]
-    pub fn read16 (&self, address: u32) -> u16 {
+    pub fn read16 (&mut self, address: u32) -> u16 {
         debug_assert!((address & 1) == 0);
+        self.addAccessCycles(address, 2);
         let mut val: u16;
 
         match address >> 24 { // these 8 bits show us which memory range the addr belongs to
@@ -116,6 +163,8 @@ impl Bus {
                 val |= (self.ppu.OAM[((address + 1) & 0x3FF) as usize] as u16) << 8;
             },
             
+            8 if self.isGPIORead(address) => val = self.gpio.read(address),
+
             8 | 9 => {
                 val = self.mem.ROM[(address - 0x8000000) as usize] as u16;
                 val |= (self.mem.ROM[(address - 0x8000000 + 1) as usize] as u16) << 8;
@@ -126,15 +175,14 @@ impl Bus {
                 val |= (self.mem.ROM[(address - 0xA000000 + 1) as usize] as u16) << 8;
             },
 
+            0xD if self.isEEPROMAccess(address) => val = self.mem.backup.getEEPROM().unwrap().read(),
+
             0xC | 0xD => {
                 val = self.mem.ROM[(address - 0xC000000) as usize] as u16;
                 val |= (self.mem.ROM[(address - 0xC000000 + 1) as usize] as u16) << 8;
             },
 
-            0xE | 0xF => {
-                val = self.mem.SRAM[(address & 0xFFFF) as usize] as u16;
-                val |= (self.mem.SRAM[((address & 0xFFFF) + 1) as usize] as u16) << 8;
-            },
+            0xE | 0xF => val = self.mem.backup.read8(address) as u16 * 0x0101, // The backup bus is 8-bit, so the byte is repeated
             
             _ => panic!("16-bit read from unimplemented mem addr {:08X}\n", address)
         }
@@ -143,8 +191,9 @@ impl Bus {
     }
 
     #[inline(always)]
-    pub fn read32 (&self, address: u32) -> u32 {
+    pub fn read32 (&mut self, address: u32) -> u32 {
         debug_assert!((address & 3) == 0);
+        self.addAccessCycles(address, 4);
         let mut val: u32;
 
         match address >> 24 { // these 8 bits show us which memory range the addr belongs to
@@ -215,12 +264,7 @@ impl Bus {
                 val |= (self.mem.ROM[(address - 0xC000000 + 3) as usize] as u32) << 24;
             },
 
-            0xE | 0xF => {
-                val = self.mem.SRAM[(address & 0xFFFF) as usize] as u32;
-                val |= (self.mem.SRAM[((address & 0xFFFF) + 1) as usize] as u32) << 8;
-                val |= (self.mem.SRAM[((address & 0xFFFF) + 2) as usize] as u32) << 16;
-                val |= (self.mem.SRAM[((address & 0xFFFF) + 3) as usize] as u32) << 24;
-            },
+            0xE | 0xF => val = self.mem.backup.read8(address) as u32 * 0x01010101,
 
             _=> panic!("32-bit read from unimplemented mem addr {:08X}\n", address)
         }
@@ -230,6 +274,7 @@ impl Bus {
 
     #[inline(always)]
     pub fn write8 (&mut self, address: u32, val: u8) {
+        self.addAccessCycles(address, 1);
         match address >> 24 {
             0 => {},
             2 => self.mem.eWRAM[(address & 0x3FFFF) as usize] = val,
@@ -243,7 +288,8 @@ impl Bus {
             6 => println!("8-bit write to VRAM!"),
             7 => self.ppu.OAM[address as usize & 0x3FF] = val,
             8..=0xD => {}
-            0xE | 0xF => self.mem.SRAM[(address & 0xFFFF) as usize] = val,
+            0xE if self.isTiltAccess(address) => self.tilt.as_mut().unwrap().write(address, val),
+            0xE | 0xF => self.mem.isBackupDirty |= self.mem.backup.write8(address, val),
             _ => todo!("Unimplemented 8-bit write at address {:08X}", address)
         }
     }
@@ -251,6 +297,7 @@ impl Bus {
     #[inline(always)]
     pub fn write16 (&mut self, address: u32, val: u16) {
         debug_assert!((address & 1) == 0); 
+        self.addAccessCycles(address, 2);
 
         match address >> 24 { // these 8 bits show us which memory range the addr belongs to
             0 => {},
@@ -289,6 +336,8 @@ impl Bus {
                 self.ppu.OAM[(address & 0x3FF) as usize] = val as u8;
                 self.ppu.OAM[((address + 1) & 0x3FF) as usize] = (val >> 8) as u8;
             }
+            8 if self.isGPIOAccess(address) => self.writeGPIO(address, val),
+            0xD if self.isEEPROMAccess(address) => self.mem.isBackupDirty |= self.mem.backup.getEEPROM().unwrap().write(val),
             8..=0xD => {}
             _ => {}//todo!("Unimplemented 16-bit write to addr {:08X}", address)
         }
@@ -297,6 +346,7 @@ impl Bus {
     #[inline(always)]
     pub fn write32 (&mut self, address: u32, val: u32) {
         debug_assert!((address & 3) == 0); 
+        self.addAccessCycles(address, 4);
         match address >> 24 { // these 8 bits show us which memory range the addr belongs to
             2 => {
                 self.mem.eWRAM[(address & 0x3FFFF) as usize] = (val & 0xFF) as u8;
@@ -349,6 +399,7 @@ impl Bus {
             0x4000006 => self.ppu.vcount as u8,
             0x4000054 => self.ppu.bldy as u8,
             0x4000089 => (self.soundbiasStub >> 8) as u8,
+            0x4000300 => self.postflg,
             _ => 0//{println!("Unimplemented 8-bit read from MMIO address {:08X}", address); 0}
         }
     }
@@ -369,10 +420,12 @@ impl Bus {
             0x400010C => self.readTimer(3),
 
             0x4000130 => self.joypad.keyinput.getRaw(),
+            0x4000134 => self.rcntStub,
             0x4000200 => self.ie,
             0x4000202 => self.getIF(),
             0x4000204 => self.waitcnt,
             0x4000208 => self.ime as u16,
+            0x4000300 => self.postflg as u16,
             //0x40000DE => self.dmaChannels[0].controlReg.getRaw(),
             _ => 0// {println!("Unimplemented 16-bit read from MMIO address {:08X}", address); 0}
         }
@@ -402,6 +455,9 @@ impl Bus {
                 self.ime = (val & 1) != 0;
                 self.scheduler.pushEvent(EventTypes::PollInterrupts, 0); // Schedule polling interrupts
             }
+            0x4000204 => self.writeWAITCNT((self.waitcnt & 0xFF00) | val as u16),
+            0x4000205 => self.writeWAITCNT((self.waitcnt & 0xFF) | ((val as u16) << 8)),
+            0x4000300 => self.postflg = val & 1,
             0x4000301 => self.halted = (val >> 7) == 0, // HALTCNT
             0x4000420 => { print!("{}", val as char); std::io::stdout().flush().ok().expect("Could not flush stdout"); }, // custom debugging port
             _ => {}//println!("Unimplemented 8-bit write to IO address {:08X}\n", address)
@@ -440,6 +496,7 @@ impl Bus {
             0x4000032 => self.ppu.aff_bg_pb[1].setRaw(val),
             0x4000034 => self.ppu.aff_bg_pc[1].setRaw(val),
             0x4000036 => self.ppu.aff_bg_pd[1].setRaw(val),
+            0x4000028..=0x400002E | 0x4000038..=0x400003E => self.ppu.writeRefPoint(address, val as u32, 0xFFFF),
             0x4000054 => self.ppu.bldy = val as u32 & 0x1F,
 
             // Timer registers
@@ -454,12 +511,14 @@ impl Bus {
             0x400010E => self.writeTMCNT16(3, val),
 
             0x4000088 => { self.soundbiasStub = (val as u32 | self.soundbiasStub & 0xFFFF0000); println!("Wrote to SOUNDBIAS!") },
+            0x4000134 => self.rcntStub = val,
+            0x4000300 => { self.postflg = val as u8 & 1; self.halted = (val >> 15) == 0 }, // POSTFLG and HALTCNT
             0x4000200 => { 
                 self.ie = val; 
                 self.scheduler.pushEvent(EventTypes::PollInterrupts, 0); // Schedule polling interrupts
             }
             0x4000202 => self.setIF(self.getIF() & !val),
-            0x4000204 => self.waitcnt = val,
+            0x4000204 => self.writeWAITCNT(val),
             0x4000208 => { 
                 self.ime = (val & 1) == 1;
                 self.scheduler.pushEvent(EventTypes::PollInterrupts, 0); // Schedule polling interrupts
@@ -514,10 +573,11 @@ impl Bus {
             0x4000014 => { self.ppu.bg_hofs[1].setRaw(val as u16); self.ppu.bg_vofs[1].setRaw((val >> 16) as u16); }
             0x4000018 => { self.ppu.bg_hofs[2].setRaw(val as u16); self.ppu.bg_vofs[2].setRaw((val >> 16) as u16); }
             0x400001C => { self.ppu.bg_hofs[3].setRaw(val as u16); self.ppu.bg_vofs[3].setRaw((val >> 16) as u16); }
-            0x4000028 => self.ppu.aff_bg_dx[0].setRaw(val),
-            0x400002C => self.ppu.aff_bg_dy[0].setRaw(val),
-            0x4000038 => self.ppu.aff_bg_dx[1].setRaw(val),
-            0x400003C => self.ppu.aff_bg_dy[1].setRaw(val),
+            0x4000020 => { self.ppu.aff_bg_pa[0].setRaw(val as u16); self.ppu.aff_bg_pb[0].setRaw((val >> 16) as u16); }
+            0x4000024 => { self.ppu.aff_bg_pc[0].setRaw(val as u16); self.ppu.aff_bg_pd[0].setRaw((val >> 16) as u16); }
+            0x4000030 => { self.ppu.aff_bg_pa[1].setRaw(val as u16); self.ppu.aff_bg_pb[1].setRaw((val >> 16) as u16); }
+            0x4000034 => { self.ppu.aff_bg_pc[1].setRaw(val as u16); self.ppu.aff_bg_pd[1].setRaw((val >> 16) as u16); }
+            0x4000028 | 0x400002C | 0x4000038 | 0x400003C => self.ppu.writeRefPoint(address, val, 0xFFFFFFF),
 
             //0x4000000..=0x4000050 => panic!("32-bit write to PPU reg: {:08X}", address),
 
@@ -527,6 +587,7 @@ impl Bus {
                 self.setIF(self.getIF() & !(val as u16));
                 if self.ime {self.scheduler.pushEvent(EventTypes::PollInterrupts, 0)}
             }
+            0x4000204 => self.writeWAITCNT(val as u16),
             0x4000208 => {
                 self.ime = (val & 1) == 1;
                 if self.ime {self.scheduler.pushEvent(EventTypes::PollInterrupts, 0)}
@@ -536,12 +597,13 @@ impl Bus {
     }
 
     pub fn getIF(&self) -> u16 {
-        self.dma_irq_requests | self.ppu.interruptFlags | self.timers.timer_interrupt_requests
+        self.dma_irq_requests | self.ppu.interruptFlags | self.timers.timer_interrupt_requests | (self.gamepak_irq_request as u16) << 13
     }
 
     pub fn setIF(&mut self, val: u16) {
         self.ppu.interruptFlags = val & 7;
         self.dma_irq_requests = (val >> 8) & 0xF;
         self.timers.timer_interrupt_requests = (val >> 3) & 0xF;
+        self.gamepak_irq_request = (val >> 13) & 1 != 0;
     }
 }
\ No newline at end of file
@@ -1,5 +1,236 @@
-use crate::bus::Bus;
+use crate::flash::{Flash, FlashManufacturer};
+use crate::eeprom::EEPROM;
+use crate::loader::readROM;
+use crate::sign_extend_32;
 
-enum save_types {
-    
-}
\ No newline at end of file
+pub const HEADER_SIZE: usize = 192;
+
+// The Nintendo logo every licensed cartridge has in its header. The BIOS refuses to boot ROMs where it doesn't match
+const NINTENDO_LOGO: [u8; 156] = [
+    0x24, 0xFF, 0xAE, 0x51, 0x69, 0x9A, 0xA2, 0x21, 0x3D, 0x84, 0x82, 0x0A, 0x84, 0xE4, 0x09, 0xAD,
+    0x11, 0x24, 0x8B, 0x98, 0xC0, 0x81, 0x7F, 0x21, 0xA3, 0x52, 0xBE, 0x19, 0x93, 0x09, 0xCE, 0x20,
+    0x10, 0x46, 0x4A, 0x4A, 0xF8, 0x27, 0x31, 0xEC, 0x58, 0xC7, 0xE8, 0x33, 0x82, 0xE3, 0xCE, 0xBF,
+    0x85, 0xF4, 0xDF, 0x94, 0xCE, 0x4B, 0x09, 0xC1, 0x94, 0x56, 0x8A, 0xC0, 0x13, 0x72, 0xA7, 0xFC,
+    0x9F, 0x84, 0x4D, 0x73, 0xA3, 0xCA, 0x9A, 0x61, 0x58, 0x97, 0xA3, 0x27, 0xFC, 0x03, 0x98, 0x76,
+    0x23, 0x1D, 0xC7, 0x61, 0x03, 0x04, 0xAE, 0x56, 0xBF, 0x38, 0x84, 0x00, 0x40, 0xA7, 0x0E, 0xFD,
+    0xFF, 0x52, 0xFE, 0x03, 0x6F, 0x95, 0x30, 0xF1, 0x97, 0xFB, 0xC0, 0x85, 0x60, 0xD6, 0x80, 0x25,
+    0xA9, 0x63, 0xBE, 0x03, 0x01, 0x4E, 0x38, 0xE2, 0xF9, 0xA2, 0x34, 0xFF, 0xBB, 0x3E, 0x03, 0x44,
+    0x78, 0x00, 0x90, 0xCB, 0x88, 0x11, 0x3A, 0x94, 0x65, 0xC0, 0x7C, 0x63, 0x87, 0xF0, 0x3C, 0xAF,
+    0xD6, 0x25, 0xE4, 0x8B, 0x38, 0x0A, 0xAC, 0x72, 0x21, 0xD4, 0xF8, 0x07
+];
+
+// The first 192 bytes of the ROM
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub entryPoint: Option<u32>, // Target of the ARM branch at 0x08000000, or None if there's no branch there
+    pub logo: Vec<u8>,
+    pub title: String,           // Up to 12 uppercase ASCII characters
+    pub gameCode: String,        // 4 characters, eg "AXVE". The last one is the region
+    pub makerCode: String,       // 2 characters, eg "01" for Nintendo
+    pub fixedValue: u8,          // Should always be 0x96
+    pub version: u8,
+    pub checksum: u8             // Complement checksum of 0xA0-0xBC
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<CartridgeHeader, String> {
+        if rom.len() < HEADER_SIZE {
+            return Err(format!("ROM is only {} bytes long, too small to contain a header", rom.len()));
+        }
+
+        let text = |start: usize, end: usize| rom[start..end].iter().take_while(|&&byte| byte != 0).map(|&byte| byte as char).collect::<String>();
+        let branch = u32::from_le_bytes([rom[0], rom[1], rom[2], rom[3]]);
+        let entryPoint = if (branch >> 24) == 0xEA { // Unconditional B
+            Some(0x08000008_u32.wrapping_add(sign_extend_32!(branch & 0xFFFFFF, 24) << 2))
+        } else {
+            None
+        };
+
+        Ok(CartridgeHeader {
+            entryPoint,
+            logo: rom[0x04..0xA0].to_vec(),
+            title: text(0xA0, 0xAC),
+            gameCode: text(0xAC, 0xB0),
+            makerCode: text(0xB0, 0xB2),
+            fixedValue: rom[0xB2],
+            version: rom[0xBC],
+            checksum: rom[0xBD]
+        })
+    }
+
+    // Read just the header of a ROM file or zipped ROM, without loading the rest of the emulator
+    pub fn fromFile(path: &str) -> Result<CartridgeHeader, String> {
+        if !std::path::Path::new(path).is_file() {
+            return Err(format!("{} is not a file", path));
+        }
+
+        CartridgeHeader::parse(&readROM(path)?)
+    }
+
+    pub fn computeChecksum(rom: &[u8]) -> u8 {
+        rom[0xA0..=0xBC].iter().fold(0_u8, |checksum, &byte| checksum.wrapping_sub(byte)).wrapping_sub(0x19)
+    }
+
+    pub fn isLogoValid(&self) -> bool {
+        self.logo[..] == NINTENDO_LOGO[..]
+    }
+
+    // Problems that would stop a real GBA from booting the ROM
+    pub fn validate(&self, rom: &[u8]) -> Vec<String> {
+        let mut warnings = vec![];
+        if !self.isLogoValid() {
+            warnings.push("Nintendo logo doesn't match".to_string());
+        }
+
+        let checksum = CartridgeHeader::computeChecksum(rom);
+        if checksum != self.checksum {
+            warnings.push(format!("Header checksum is {:02X}, expected {:02X}", self.checksum, checksum));
+        }
+
+        if self.fixedValue != 0x96 {
+            warnings.push(format!("Fixed header value is {:02X}, expected 96", self.fixedValue));
+        }
+
+        if self.entryPoint.is_none() {
+            warnings.push("No branch to the entry point at the start of the ROM".to_string());
+        }
+
+        warnings
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum save_types {
+    None,
+    SRAM,      // Also used for FRAM (SRAM_F_V), which behaves the same
+    EEPROM,    // 512 bytes or 8KB, detected when the game first accesses it
+    Flash64K,
+    Flash128K
+}
+
+// Nintendo's save library embeds one of these ID strings in the ROM, followed by a version number
+const SAVE_TYPE_SIGNATURES: [(&[u8], save_types); 6] = [
+    (b"EEPROM_V",   save_types::EEPROM),
+    (b"SRAM_V",     save_types::SRAM),
+    (b"SRAM_F_V",   save_types::SRAM),
+    (b"FLASH_V",    save_types::Flash64K),
+    (b"FLASH512_V", save_types::Flash64K),
+    (b"FLASH1M_V",  save_types::Flash128K)
+];
+
+impl save_types {
+    // Games without a signature are given SRAM, since that's what most homebrew expects
+    pub fn detect(rom: &[u8]) -> save_types {
+        for (signature, saveType) in SAVE_TYPE_SIGNATURES.iter() {
+            if rom.windows(signature.len()).any(|window| window == *signature) {
+                return *saveType;
+            }
+        }
+
+        save_types::SRAM
+    }
+
+    // Parse a save type name, for overriding the detected type of games that report the wrong one
+    pub fn fromName(name: &str) -> Option<save_types> {
+        match name.to_lowercase().as_str() {
+            "none" => Some(save_types::None),
+            "sram" => Some(save_types::SRAM),
+            "eeprom" => Some(save_types::EEPROM),
+            "flash" | "flash64k" | "flash512" => Some(save_types::Flash64K),
+            "flash128k" | "flash1m" => Some(save_types::Flash128K),
+            _ => None
+        }
+    }
+}
+
+// The backup memory mapped at 0x0E000000-0x0FFFFFFF
+pub enum Backup {
+    None,
+    SRAM(Vec<u8>),
+    Flash(Flash),
+    EEPROM(EEPROM) // Mapped to 0x0D000000 instead, so reads from 0x0E000000 return open bus
+}
+
+impl Backup {
+    // If no Flash manufacturer is given, the one real carts with that chip size use is picked
+    pub fn new(saveType: save_types, flashManufacturer: Option<FlashManufacturer>) -> Backup {
+        match saveType {
+            save_types::None => Backup::None,
+            save_types::SRAM => Backup::SRAM(vec![0; 64 * 1024]),
+            save_types::EEPROM => Backup::EEPROM(EEPROM::new()),
+            save_types::Flash64K => Backup::Flash(Flash::new(false, flashManufacturer.unwrap_or(FlashManufacturer::default(false)))),
+            save_types::Flash128K => Backup::Flash(Flash::new(true, flashManufacturer.unwrap_or(FlashManufacturer::default(true))))
+        }
+    }
+
+    pub fn read8(&self, address: u32) -> u8 {
+        match self {
+            Backup::SRAM(sram) => sram[(address & 0xFFFF) as usize],
+            Backup::Flash(flash) => flash.read(address),
+            Backup::None | Backup::EEPROM(_) => 0xFF
+        }
+    }
+
+    // Returns whether the stored data changed, ie whether the save file needs to be rewritten
+    pub fn write8(&mut self, address: u32, val: u8) -> bool {
+        match self {
+            Backup::SRAM(sram) => {
+                let byte = &mut sram[(address & 0xFFFF) as usize];
+                let changed = *byte != val;
+                *byte = val;
+                changed
+            }
+
+            Backup::Flash(flash) => flash.write(address, val),
+            Backup::None | Backup::EEPROM(_) => false
+        }
+    }
+
+    // Raw backup memory contents, in the same layout other emulators use for .sav files
+    pub fn getData(&self) -> &[u8] {
+        match self {
+            Backup::None => &[],
+            Backup::SRAM(sram) => sram,
+            Backup::Flash(flash) => &flash.memory,
+            Backup::EEPROM(eeprom) => &eeprom.memory
+        }
+    }
+
+    // Load a .sav file. Its size tells us the size of Flash and EEPROM chips
+    pub fn loadData(&mut self, data: &[u8], flashManufacturer: Option<FlashManufacturer>) {
+        match self {
+            Backup::None => {}
+            Backup::SRAM(sram) => {
+                let len = data.len().min(sram.len());
+                sram[..len].copy_from_slice(&data[..len]);
+            }
+
+            Backup::Flash(flash) => {
+                if data.len() == 128 * 1024 && !flash.is128K() {
+                    *flash = Flash::new(true, flashManufacturer.unwrap_or(FlashManufacturer::default(true)));
+                }
+
+                let len = data.len().min(flash.memory.len());
+                flash.memory[..len].copy_from_slice(&data[..len]);
+            }
+
+            Backup::EEPROM(eeprom) => {
+                match data.len() {
+                    512 => eeprom.setAddressBits(6),
+                    8192 => eeprom.setAddressBits(14),
+                    _ => println!("Unexpected EEPROM save size: {} bytes", data.len())
+                }
+
+                let len = data.len().min(eeprom.memory.len());
+                eeprom.memory[..len].copy_from_slice(&data[..len]);
+            }
+        }
+    }
+
+    pub fn getEEPROM(&mut self) -> Option<&mut EEPROM> {
+        match self {
+            Backup::EEPROM(eeprom) => Some(eeprom),
+            _ => None
+        }
+    }
+}
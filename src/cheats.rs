@@ -0,0 +1,551 @@
+use crate::bus::Bus;
+use crate::helpers::readFileIntoVec;
+use std::path::Path;
+
+// Cheat devices. GameShark and Action Replay v1/v2 codes share one encryption, Action Replay v3 (also sold as GameShark SP)
+// uses the same algorithm with different seeds. CodeBreaker codes are plain text, unless a 9xxxxxxx code turns on encryption.
+// The real devices hook into the game's code to run once per frame, so here cheats just run at the end of every frame
+const GAMESHARK_SEEDS: [u32; 4] = [0x09F4FBBD, 0x9681884A, 0x352027E9, 0xF3DEE5A7];
+const ACTION_REPLAY_V3_SEEDS: [u32; 4] = [0x7AA9648F, 0x7FAE6994, 0xC0EFAAD5, 0x42712C57];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheatDevice {
+    GameShark,      // GameShark and Action Replay v1/v2
+    ActionReplayV3, // Action Replay v3 and GameShark SP
+    CodeBreaker
+}
+
+impl CheatDevice {
+    pub fn fromName(name: &str) -> Option<CheatDevice> {
+        match name.to_lowercase().as_str() {
+            "gameshark" | "gs" | "actionreplay" | "ar" | "arv1" | "arv2" => Some(CheatDevice::GameShark),
+            "actionreplayv3" | "arv3" | "gssp" => Some(CheatDevice::ActionReplayV3),
+            "codebreaker" | "cb" => Some(CheatDevice::CodeBreaker),
+            _ => None
+        }
+    }
+}
+
+// GameShark and Action Replay codes are encrypted with TEA. CodeBreaker encryption depends on earlier codes, see CodeBreakerKey
+pub fn decrypt(device: CheatDevice, mut op1: u32, mut op2: u32) -> (u32, u32) {
+    let seeds = match device {
+        CheatDevice::GameShark => GAMESHARK_SEEDS,
+        CheatDevice::ActionReplayV3 => ACTION_REPLAY_V3_SEEDS,
+        CheatDevice::CodeBreaker => return (op1, op2)
+    };
+
+    let mut sum = 0xC6EF3720_u32;
+    for _ in 0..32 {
+        op2 = op2.wrapping_sub((op1 << 4).wrapping_add(seeds[2]) ^ op1.wrapping_add(sum) ^ (op1 >> 5).wrapping_add(seeds[3]));
+        op1 = op1.wrapping_sub((op2 << 4).wrapping_add(seeds[0]) ^ op2.wrapping_add(sum) ^ (op2 >> 5).wrapping_add(seeds[1]));
+        sum = sum.wrapping_sub(0x9E3779B9);
+    }
+
+    (op1, op2)
+}
+
+// CodeBreaker encryption, set up by a 9xxxxxxx yyyy code. Codes after it are decrypted by shuffling their 48 bits
+// around with a table, then XORing them with seeds. The table and seeds come from an RNG seeded with parts of the 9 code
+struct CodeBreakerKey {
+    table: [u8; 48],
+    seeds: [u32; 4],
+    master: u32 // The 9 code itself
+}
+
+impl CodeBreakerKey {
+    fn new(op1: u32, op2: u32) -> CodeBreakerKey {
+        let mut key = CodeBreakerKey { table: [0; 48], seeds: [0; 4], master: op1 };
+        for i in 0..48 {
+            key.table[i] = i as u8;
+        }
+
+        let mut state = (op2 & 0xFF) ^ 0x1111;
+        for _ in 0..0x50 {
+            let x = (Self::random(&mut state) % 48) as usize;
+            let y = (Self::random(&mut state) % 48) as usize;
+            key.table.swap(x, y);
+        }
+
+        state = 0x4EFAD1C3;
+        for _ in 0..((op1 >> 24) & 0xF) {
+            state = Self::random(&mut state);
+        }
+
+        key.seeds[2] = Self::random(&mut state);
+        key.seeds[3] = Self::random(&mut state);
+
+        state = ((op2 >> 8) & 0xFF) ^ 0xF254;
+        for _ in 0..((op2 >> 8) & 0xFF) {
+            state = Self::random(&mut state);
+        }
+
+        key.seeds[0] = Self::random(&mut state);
+        key.seeds[1] = Self::random(&mut state);
+        key
+    }
+
+    // The LCG is rolled 3 times, with the top bits of each roll mixed into a 32-bit number
+    fn random(state: &mut u32) -> u32 {
+        let roll = |x: u32| x.wrapping_mul(0x41C64E6D).wrapping_add(0x3039);
+        let roll1 = roll(*state);
+        let roll2 = roll(roll1);
+        let roll3 = roll(roll2);
+
+        *state = roll3;
+        ((roll1 << 14) & 0xC0000000) | ((roll2 >> 1) & 0x3FFF8000) | ((roll3 >> 16) & 0x7FFF)
+    }
+
+    // Codes are handled as 6 big endian bytes: 4 for the first half and 2 for the second
+    fn toBytes(op1: u32, op2: u32) -> [u8; 6] {
+        let [a, b, c, d] = op1.to_be_bytes();
+        let [e, f] = (op2 as u16).to_be_bytes();
+        [a, b, c, d, e, f]
+    }
+
+    fn fromBytes(bytes: [u8; 6]) -> (u32, u32) {
+        (u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]), u16::from_be_bytes([bytes[4], bytes[5]]) as u32)
+    }
+
+    fn decrypt(&self, op1: u32, op2: u32) -> (u32, u32) {
+        let mut bytes = Self::toBytes(op1, op2);
+        for i in (0..48).rev() { // Swap bit i with the bit the table says
+            let (byteX, bitX) = (i >> 3, i & 7);
+            let (byteY, bitY) = ((self.table[i] >> 3) as usize, self.table[i] & 7);
+            let x = (bytes[byteX] >> bitX) & 1;
+            let y = (bytes[byteY] >> bitY) & 1;
+
+            bytes[byteX] = (bytes[byteX] & !(1 << bitX)) | (y << bitX);
+            bytes[byteY] = (bytes[byteY] & !(1 << bitY)) | (x << bitY);
+        }
+
+        let (op1, op2) = Self::fromBytes(bytes);
+        let mut bytes = Self::toBytes(op1 ^ self.seeds[0], op2 ^ self.seeds[1]);
+        let masterLow = self.master as u8;
+        let masterHigh = (self.master >> 8) as u8;
+
+        for i in 0..5 {
+            bytes[i] ^= masterHigh ^ bytes[i + 1];
+        }
+        bytes[5] ^= masterHigh;
+
+        for i in (1..6).rev() {
+            bytes[i] ^= masterLow ^ bytes[i - 1];
+        }
+        bytes[0] ^= masterLow;
+
+        let (op1, op2) = Self::fromBytes(bytes);
+        (op1 ^ self.seeds[2], (op2 ^ self.seeds[3]) & 0xFFFF)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Condition {
+    Equal,
+    NotEqual,
+    Less,            // Signed
+    Greater,
+    LessUnsigned,
+    GreaterUnsigned,
+    And              // Any of the bits are set
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Skip {
+    Lines(usize), // Skip this many code lines if the condition fails
+    Block,        // Skip until the matching else/endif
+    Rest          // Skip the rest of the cheat
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Modify {
+    Add,
+    Or,
+    And
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum CheatOp {
+    Write { address: u32, width: u32, value: u32, count: u32 }, // Fill count consecutive units
+    WriteIndirect { pointer: u32, offset: u32, width: u32, value: u32 },
+    Modify { address: u32, width: u32, modify: Modify, value: u32 },
+    If { address: u32, width: u32, condition: Condition, value: u32, skip: Skip },
+    IfKeys { keys: u16, skip: Skip }, // All of these keys are held
+    Else,
+    EndIf,
+    ROMPatch { address: u32, value: u16 },
+    Nop // Master codes, hooks and game IDs, which only matter on real hardware
+}
+
+pub struct Cheat {
+    pub name: String,
+    pub enabled: bool,
+    ops: Vec<(CheatOp, usize)> // Each op and how many code lines it was made from
+}
+
+impl Cheat {
+    // Codes are one per line, as "XXXXXXXX YYYYYYYY" (GameShark/Action Replay) or "XXXXXXXX YYYY" (CodeBreaker)
+    pub fn new(name: &str, device: CheatDevice, codes: &str) -> Result<Cheat, String> {
+        let codes = codes.lines().map(str::trim).filter(|line| !line.is_empty())
+                         .map(|line| parseCode(line, device)).collect::<Result<Vec<_>, String>>()?;
+        let mut codes = codes.iter();
+        let mut ops = vec![];
+        let mut codeBreakerKey: Option<CodeBreakerKey> = None;
+
+        while let Some(&(op1, op2)) = codes.next() {
+            let linesLeft = codes.len() + 1;
+            let op = match device {
+                CheatDevice::GameShark => decodeGameShark(op1, op2),
+                CheatDevice::ActionReplayV3 => decodeActionReplayV3(op1, op2, &mut codes),
+                CheatDevice::CodeBreaker => {
+                    let (op1, op2) = codeBreakerKey.as_ref().map_or((op1, op2), |key| key.decrypt(op1, op2));
+                    if (op1 >> 28) == 9 { // Encryption key for the codes after this one
+                        codeBreakerKey = Some(CodeBreakerKey::new(op1, op2));
+                        Ok(CheatOp::Nop)
+                    } else {
+                        decodeCodeBreaker(op1, op2)
+                    }
+                }
+            };
+
+            ops.push((op.map_err(|e| format!("{:08X} {:08X}: {}", op1, op2, e))?, linesLeft - codes.len())); // AR v3 ROM patches take 2 lines
+        }
+
+        Ok(Cheat { name: name.to_string(), enabled: true, ops })
+    }
+}
+
+// Split a code line into its 2 halves and decrypt them. CodeBreaker codes are decrypted in Cheat::new instead
+fn parseCode(line: &str, device: CheatDevice) -> Result<(u32, u32), String> {
+    let digits: String = line.chars().filter(|c| !c.is_whitespace() && *c != ':').collect();
+    let expectedLength = if device == CheatDevice::CodeBreaker { 12 } else { 16 };
+    if digits.len() != expectedLength || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid code \"{}\", expected {} hex digits", line, expectedLength));
+    }
+
+    let op1 = u32::from_str_radix(&digits[..8], 16).unwrap();
+    let op2 = u32::from_str_radix(&digits[8..], 16).unwrap();
+    Ok(decrypt(device, op1, op2))
+}
+
+// Cheats can only touch memory that the bus can write to
+fn checkAddress(address: u32) -> Result<u32, String> {
+    if (2..=7).contains(&(address >> 24)) {
+        Ok(address)
+    } else {
+        Err(format!("Address {:08X} isn't in RAM or IO", address))
+    }
+}
+
+fn decodeGameShark(op1: u32, op2: u32) -> Result<CheatOp, String> {
+    let address = op1 & 0x0FFFFFFF;
+    match op1 >> 28 {
+        _ if op1 == 0xDEADFACE => Err("Encryption seed changes aren't supported".to_string()),
+        0 => Ok(CheatOp::Write { address: checkAddress(address)?, width: 1, value: op2 & 0xFF, count: 1 }),
+        1 => Ok(CheatOp::Write { address: checkAddress(address)?, width: 2, value: op2 & 0xFFFF, count: 1 }),
+        2 => Ok(CheatOp::Write { address: checkAddress(address)?, width: 4, value: op2, count: 1 }),
+        6 => Ok(CheatOp::ROMPatch { address: 0x08000000 + ((op1 & 0xFFFFFF) << 1), value: op2 as u16 }),
+        0xD => Ok(CheatOp::If { address: checkAddress(address)?, width: 2, condition: Condition::Equal, value: op2 & 0xFFFF, skip: Skip::Lines(1) }),
+        0xE => Ok(CheatOp::If { // E0ccvvvv aaaaaaaa: If [a] == v, run the next c codes
+            address: checkAddress(op2 & 0x0FFFFFFF)?, width: 2, condition: Condition::Equal,
+            value: op1 & 0xFFFF, skip: Skip::Lines(((op1 >> 16) & 0xFF) as usize)
+        }),
+        0xF => Ok(CheatOp::Nop), // Master code
+        _ => Err("Unsupported code type".to_string())
+    }
+}
+
+fn decodeActionReplayV3(op1: u32, op2: u32, codes: &mut std::slice::Iter<(u32, u32)>) -> Result<CheatOp, String> {
+    if op2 == 0x001DC0DE { // Game ID
+        return Ok(CheatOp::Nop);
+    }
+
+    if op1 == 0xDEADFACE {
+        return Err("Encryption seed changes aren't supported".to_string());
+    }
+
+    if op1 == 0 { // Special codes, selected by the top byte of the second half
+        return match op2 >> 24 {
+            0x00 => Ok(CheatOp::Nop),
+            0x18 | 0x1A | 0x1C | 0x1E => { // ROM patch. The value is in the next code
+                let &(value, _) = codes.next().ok_or("ROM patch is missing its value")?;
+                Ok(CheatOp::ROMPatch { address: 0x08000000 | ((op2 & 0xFFFFFF) << 1), value: value as u16 })
+            }
+
+            0x40 => Ok(CheatOp::EndIf),
+            0x60 => Ok(CheatOp::Else),
+            _ => Err("Unsupported code type".to_string())
+        };
+    }
+
+    // The address is packed as a0aaaaa, with the region in bits 20-23
+    let address = (op1 & 0xFFFFF) | ((op1 << 4) & 0x0F000000);
+    let width = match (op1 >> 25) & 3 {
+        0 => 1,
+        1 => 2,
+        2 => 4,
+        _ => return Err("Invalid data size".to_string())
+    };
+
+    let mask = if width == 4 { 0xFFFFFFFF } else { (1 << (width * 8)) - 1 };
+    let condition = match (op1 >> 27) & 7 {
+        0 => None,
+        1 => Some(Condition::Equal),
+        2 => Some(Condition::NotEqual),
+        3 => Some(Condition::Less),
+        4 => Some(Condition::Greater),
+        5 => Some(Condition::LessUnsigned),
+        6 => Some(Condition::GreaterUnsigned),
+        _ => Some(Condition::And)
+    };
+
+    if let Some(condition) = condition { // The top 2 bits are what to do if the condition fails
+        let skip = match op1 >> 30 {
+            0 => Skip::Lines(1),
+            1 => Skip::Lines(2),
+            2 => Skip::Block,
+            _ => Skip::Rest
+        };
+
+        return Ok(CheatOp::If { address: checkAddress(address)?, width, condition, value: op2 & mask, skip });
+    }
+
+    // For unconditional codes, the top 2 bits are the operation. The bits of the second half above the value are a count/offset
+    let extra = if width == 4 { 0 } else { op2 >> (width * 8) };
+    match op1 >> 30 {
+        0 => Ok(CheatOp::Write { address: checkAddress(address)?, width, value: op2 & mask, count: extra + 1 }),
+        1 => Ok(CheatOp::WriteIndirect { pointer: checkAddress(address)?, offset: extra * width, width, value: op2 & mask }),
+        2 => Ok(CheatOp::Modify { address: checkAddress(address)?, width, modify: Modify::Add, value: op2 & mask }),
+        _ => match op1 >> 24 {
+            0xC4 => Ok(CheatOp::Nop), // Hook
+            0xC6 => Ok(CheatOp::Write { address: 0x04000000 | (op1 & 0x3FF), width: 2, value: op2 & 0xFFFF, count: 1 }),
+            0xC7 => Ok(CheatOp::Write { address: 0x04000000 | (op1 & 0x3FF), width: 4, value: op2, count: 1 }),
+            _ => Err("Unsupported code type".to_string())
+        }
+    }
+}
+
+fn decodeCodeBreaker(op1: u32, op2: u32) -> Result<CheatOp, String> {
+    let address = op1 & 0x0FFFFFFF;
+    let ifCode = |condition| -> Result<CheatOp, String> {
+        Ok(CheatOp::If { address: checkAddress(address)?, width: 2, condition, value: op2, skip: Skip::Lines(1) })
+    };
+
+    match op1 >> 28 {
+        0 | 1 => Ok(CheatOp::Nop), // Master code: Game ID and hook
+        2 => Ok(CheatOp::Modify { address: checkAddress(address)?, width: 2, modify: Modify::Or, value: op2 }),
+        3 => Ok(CheatOp::Write { address: checkAddress(address)?, width: 1, value: op2 & 0xFF, count: 1 }),
+        6 => Ok(CheatOp::Modify { address: checkAddress(address)?, width: 2, modify: Modify::And, value: op2 }),
+        7 => ifCode(Condition::Equal),
+        8 => Ok(CheatOp::Write { address: checkAddress(address)?, width: 2, value: op2, count: 1 }),
+        0xA => ifCode(Condition::NotEqual),
+        0xB => ifCode(Condition::GreaterUnsigned),
+        0xC => ifCode(Condition::LessUnsigned),
+        0xD if address == 0x20 => Ok(CheatOp::IfKeys { keys: op2 as u16 & 0x3FF, skip: Skip::Lines(1) }),
+        0xE => Ok(CheatOp::Modify { address: checkAddress(address)?, width: 2, modify: Modify::Add, value: op2 }),
+        0xF => ifCode(Condition::And),
+        _ => Err("Unsupported code type".to_string())
+    }
+}
+
+// Skip the ops making up the count code lines after op i. Returns the index of the last skipped op
+fn skipLines(ops: &[(CheatOp, usize)], mut i: usize, count: usize) -> usize {
+    let mut skipped = 0;
+    while skipped < count && i + 1 < ops.len() {
+        i += 1;
+        skipped += ops[i].1;
+    }
+
+    i
+}
+
+fn compare(condition: Condition, val: u32, target: u32, width: u32) -> bool {
+    let shift = 32 - width * 8;
+    let signed = |x: u32| ((x << shift) as i32) >> shift;
+
+    match condition {
+        Condition::Equal => val == target,
+        Condition::NotEqual => val != target,
+        Condition::Less => signed(val) < signed(target),
+        Condition::Greater => signed(val) > signed(target),
+        Condition::LessUnsigned => val < target,
+        Condition::GreaterUnsigned => val > target,
+        Condition::And => (val & target) != 0
+    }
+}
+
+pub struct Cheats {
+    pub list: Vec<Cheat>,
+    romPatches: Vec<(u32, u16)> // ROM halfwords changed by patch codes, and their original values
+}
+
+impl Cheats {
+    pub fn new() -> Cheats {
+        Cheats {
+            list: vec![],
+            romPatches: vec![]
+        }
+    }
+
+    // Cheat files have the name of each cheat in brackets, followed by an optional "type = <device>" line and its codes.
+    // Without a type, 12 digit codes are treated as CodeBreaker and 16 digit ones as GameShark. Lines starting with # are comments.
+    // Returns how many cheats were loaded
+    pub fn loadFile(&mut self, path: &Path) -> Result<usize, String> {
+        let text = String::from_utf8_lossy(&readFileIntoVec(&path.to_string_lossy())?).to_string();
+        let mut cheats = vec![];
+        let mut current: Option<(String, Option<CheatDevice>, String)> = None; // Name, device and codes of the cheat being read
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                cheats.extend(current.take());
+                current = Some((line[1..line.len() - 1].trim().to_string(), None, String::new()));
+                continue;
+            }
+
+            let (_, device, codes) = current.as_mut().ok_or(format!("Line {}: Code outside of a cheat", number + 1))?;
+            if let Some(name) = line.strip_prefix("type").map(str::trim).and_then(|rest| rest.strip_prefix('=')) {
+                *device = Some(CheatDevice::fromName(name.trim()).ok_or(format!("Line {}: Unknown cheat type {}", number + 1, name.trim()))?);
+            } else {
+                codes.push_str(line);
+                codes.push('\n');
+            }
+        }
+
+        cheats.extend(current.take());
+        let mut count = 0;
+        for (name, device, codes) in cheats {
+            let device = device.unwrap_or_else(|| {
+                let digits = codes.lines().next().unwrap_or("").chars().filter(|c| c.is_ascii_hexdigit()).count();
+                if digits == 12 { CheatDevice::CodeBreaker } else { CheatDevice::GameShark }
+            });
+
+            // Skip cheats that fail to parse, so one bad cheat doesn't lose the rest of the file
+            match Cheat::new(&name, device, &codes) {
+                Ok(cheat) => { self.list.push(cheat); count += 1; }
+                Err(e) => println!("Warning: Skipping cheat \"{}\": {}", name, e)
+            }
+        }
+
+        Ok(count)
+    }
+
+    // Put back the ROM data changed by patch codes
+    fn restoreROM(&mut self, rom: &mut [u8]) {
+        for (address, original) in self.romPatches.drain(..).rev() { // In reverse, in case 2 cheats patch the same address
+            let offset = (address & 0x1FFFFFE) as usize;
+            rom[offset..offset + 2].copy_from_slice(&original.to_le_bytes());
+        }
+    }
+}
+
+impl Bus {
+    // Run all enabled cheats. Called once per frame
+    pub fn runCheats(&mut self) {
+        self.cheats.restoreROM(&mut self.mem.ROM); // Patches are reapplied below if their cheat is still enabled
+        if self.cheats.list.is_empty() {
+            return;
+        }
+
+        // Cheat accesses don't take any time
+        let pendingCycles = self.waitstates.pendingCycles;
+        let nextSeqAddress = self.waitstates.nextSeqAddress;
+        let cheats = std::mem::take(&mut self.cheats.list);
+
+        for cheat in cheats.iter().filter(|cheat| cheat.enabled) {
+            self.runCheat(&cheat.ops);
+        }
+
+        self.cheats.list = cheats;
+        self.waitstates.pendingCycles = pendingCycles;
+        self.waitstates.nextSeqAddress = nextSeqAddress;
+    }
+
+    fn runCheat(&mut self, ops: &[(CheatOp, usize)]) {
+        let mut blocks: Vec<bool> = vec![]; // Whether each if-block we're in is running
+        let mut i = 0;
+
+        while i < ops.len() {
+            let isActive = blocks.iter().all(|&active| active);
+            match ops[i].0 {
+                CheatOp::Else => if let Some(active) = blocks.last_mut() { *active = !*active },
+                CheatOp::EndIf => { blocks.pop(); }
+                CheatOp::If { skip: Skip::Block, .. } if !isActive => blocks.push(false), // Still needs to match its endif
+                _ if !isActive => {}
+
+                CheatOp::If { address, width, condition, value, skip } => {
+                    let val = self.readCheatValue(address, width);
+                    match skip {
+                        Skip::Block => blocks.push(compare(condition, val, value, width)),
+                        Skip::Lines(count) if !compare(condition, val, value, width) => i = skipLines(ops, i, count),
+                        Skip::Rest if !compare(condition, val, value, width) => return,
+                        _ => {}
+                    }
+                }
+
+                CheatOp::IfKeys { keys, skip } => {
+                    let pressedKeys = !self.joypad.keyinput.getRaw() & 0x3FF;
+                    if let Skip::Lines(count) = skip {
+                        if (pressedKeys & keys) != keys {
+                            i = skipLines(ops, i, count);
+                        }
+                    }
+                }
+
+                CheatOp::Write { address, width, value, count } => {
+                    for unit in 0..count {
+                        self.writeCheatValue(address + unit * width, width, value);
+                    }
+                }
+
+                CheatOp::WriteIndirect { pointer, offset, width, value } => {
+                    let address = self.readCheatValue(pointer, 4).wrapping_add(offset);
+                    if checkAddress(address).is_ok() { // The pointer comes from the game, so it might point anywhere
+                        self.writeCheatValue(address, width, value);
+                    }
+                }
+
+                CheatOp::Modify { address, width, modify, value } => {
+                    let val = self.readCheatValue(address, width);
+                    let val = match modify {
+                        Modify::Add => val.wrapping_add(value),
+                        Modify::Or => val | value,
+                        Modify::And => val & value
+                    };
+
+                    self.writeCheatValue(address, width, val);
+                }
+
+                CheatOp::ROMPatch { address, value } => {
+                    let offset = (address & 0x1FFFFFE) as usize; // ROM is mirrored in all 3 wait state regions
+                    let original = u16::from_le_bytes([self.mem.ROM[offset], self.mem.ROM[offset + 1]]);
+                    self.cheats.romPatches.push((address, original));
+                    self.mem.ROM[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+                }
+
+                CheatOp::Nop => {}
+            }
+
+            i += 1;
+        }
+    }
+
+    fn readCheatValue(&mut self, address: u32, width: u32) -> u32 {
+        match width {
+            1 => self.read8(address) as u32,
+            2 => self.read16(address & !1) as u32,
+            _ => self.read32(address & !3)
+        }
+    }
+
+    fn writeCheatValue(&mut self, address: u32, width: u32, val: u32) {
+        match width {
+            1 => self.write8(address, val as u8),
+            2 => self.write16(address & !1, val as u16),
+            _ => self.write32(address & !3, val)
+        }
+    }
+}
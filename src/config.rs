@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Frontend settings, read from a config file with one "key = value" per line. Lines starting with # are comments.
+// Keys:
+//   bios: Path to the BIOS image. Relative paths are relative to the config file
+//   flash_manufacturer: Flash chip ID to report (macronix, panasonic, sanyo or sst). Overridden by --flash-manufacturer
+//   save_type: Backup memory type to use instead of the detected one. Overridden by --save-type
+//   save_type.<game code>: Same, but only for the game with that code in its header, eg save_type.AXVE. Takes priority over save_type
+pub const DEFAULT_CONFIG_PATH: &str = "beeg.cfg";
+pub const BIOS_ENV_VAR: &str = "BEEG_BIOS";
+const DEFAULT_BIOS_PATH: &str = "ROMs/NormattBIOS.gba"; // Used if no BIOS is configured, as long as it exists
+
+#[derive(Default)]
+pub struct Config {
+    values: HashMap<String, String>,
+    directory: PathBuf // Directory the config file is in
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Couldn't read config file {}: {}", path, e))?;
+        let mut values = HashMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or(format!("{} line {}: Expected \"key = value\"", path, number + 1))?;
+            values.insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+
+        Ok(Config {
+            values,
+            directory: Path::new(path).parent().unwrap_or(Path::new("")).to_path_buf()
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    // Where to load the BIOS from. In order: the path given on the command line, the BEEG_BIOS environment variable,
+    // the config file, then ROMs/NormattBIOS.gba if it exists. None means there's no BIOS image to use
+    pub fn biosPath(&self, cliPath: Option<String>) -> Option<String> {
+        cliPath.or_else(|| std::env::var(BIOS_ENV_VAR).ok().filter(|path| !path.is_empty()))
+               .or_else(|| self.get("bios").map(|path| self.directory.join(path).to_string_lossy().to_string()))
+               .or_else(|| Some(DEFAULT_BIOS_PATH.to_string()).filter(|path| Path::new(path).exists()))
+    }
+}
@@ -35,7 +35,9 @@ pub struct CPU {
 
     pub r13_banks: [u32; 6],
     pub r14_banks: [u32; 6],
-    pub spsr_banks: [PSR; 5]
+    pub spsr_banks: [PSR; 5],
+
+    pub isInIntrWait: bool // Whether an HLE IntrWait is waiting for an interrupt
 }
 
 pub enum CPUStates {
@@ -71,27 +73,39 @@ impl CPU {
         
             r13_banks: [0; 6],
             r14_banks: [0; 6],
-            spsr_banks: [PSR(0), PSR(0), PSR(0), PSR(0), PSR(0)]
+            spsr_banks: [PSR(0), PSR(0), PSR(0), PSR(0), PSR(0)],
+
+            isInIntrWait: false
+        }
+    }
+
+    // Direct boot skips the BIOS intro and starts at the ROM with the state the BIOS leaves behind.
+    // The HLE BIOS has no boot code, so it always boots directly
+    pub fn init(&mut self, bus: &mut Bus, directBoot: bool) {
+        if directBoot || bus.isHLEBios() {
+            self.skipBIOS(bus.mem.entryPoint);
+            bus.skipBIOS();
+        }
+
+        else {
+            self.gprs[13] = 0x03007F00;
+            self.gprs[15] = 0;
         }
+
+        self.refillPipeline(bus);
+        self.populateARMLut();
+        self.populateThumbLUT();
     }
 
-    pub fn init(&mut self, bus: &Bus) {
-    // For skipping BIOS:
-    /*  
+    pub fn skipBIOS(&mut self, entryPoint: u32) {
         self.setCPSR(0x6000001F);
         self.gprs[0] = 0x08000000;
         self.gprs[1] = 0x000000EA;
         self.gprs[13] = 0x03007F00;
-        self.r13_banks[2] = 0x03007FE0; // SP SVC
-        self.r13_banks[4] = 0x03007FA0; // SP IRQ
+        self.r13_banks[2] = 0x03007FA0; // SP IRQ
+        self.r13_banks[3] = 0x03007FE0; // SP SVC
 
-        self.gprs[15] = 0x08000000;
-    */
-        self.gprs[13] = 0x03007F00;
-        self.gprs[15] = 0;
-        self.refillPipeline(bus);
-        self.populateARMLut();
-        self.populateThumbLUT();
+        self.gprs[15] = entryPoint;
     }
 
     #[inline(always)]
@@ -108,16 +122,16 @@ impl CPU {
         else {
             if self.isInARMState() { // TODO: Fix this hack-ish pipeline thing
                 self.gprs[15] = (val - 4) & !3;
-                self.pipeline[1] = bus.read32(self.gprs[15] + 4); 
-                self.pipeline[2] = bus.read32(self.gprs[15] + 8);
+                self.pipeline[1] = bus.fetch32(self.gprs[15] + 4); 
+                self.pipeline[2] = bus.fetch32(self.gprs[15] + 8);
 
                 self.gprs[15] += 8;
             }
             
             else {
                 self.gprs[15] = (val - 2) & !1;
-                self.pipeline[1] = bus.read16(self.gprs[15] + 2) as u32; 
-                self.pipeline[2] = bus.read16(self.gprs[15] + 4) as u32;
+                self.pipeline[1] = bus.fetch16(self.gprs[15] + 2) as u32; 
+                self.pipeline[2] = bus.fetch16(self.gprs[15] + 4) as u32;
 
                 self.gprs[15] += 4;
             }
@@ -141,34 +155,34 @@ impl CPU {
         self.advancePipeline(bus);
     }
 
-    pub fn advancePipeline(&mut self, bus: &Bus) {
+    pub fn advancePipeline(&mut self, bus: &mut Bus) {
         self.pipeline[0] = self.pipeline[1];
         self.pipeline[1] = self.pipeline[2];
         
         if self.isInARMState() {
                 self.gprs[15] += 4;
-                self.pipeline[2] = bus.read32(self.gprs[15]);
+                self.pipeline[2] = bus.fetch32(self.gprs[15]);
         }
 
         else {
                 self.gprs[15] += 2;
-                self.pipeline[2] = bus.read16(self.gprs[15]) as u32;
+                self.pipeline[2] = bus.fetch16(self.gprs[15]) as u32;
         }
     }
 
     #[inline(always)]
-    pub fn refillPipeline (&mut self, bus: &Bus) {
+    pub fn refillPipeline (&mut self, bus: &mut Bus) {
         if self.isInARMState() {
-            self.pipeline[0] = bus.read32(self.gprs[15]);
-            self.pipeline[1] = bus.read32(self.gprs[15] + 4);
-            self.pipeline[2] = bus.read32(self.gprs[15] + 8);
+            self.pipeline[0] = bus.fetch32(self.gprs[15]);
+            self.pipeline[1] = bus.fetch32(self.gprs[15] + 4);
+            self.pipeline[2] = bus.fetch32(self.gprs[15] + 8);
             self.gprs[15] += 8;
         }
 
         else {
-            self.pipeline[0] = bus.read16(self.gprs[15]) as u32;
-            self.pipeline[1] = bus.read16(self.gprs[15] + 2) as u32;
-            self.pipeline[2] = bus.read16(self.gprs[15] + 4) as u32;
+            self.pipeline[0] = bus.fetch16(self.gprs[15]) as u32;
+            self.pipeline[1] = bus.fetch16(self.gprs[15] + 2) as u32;
+            self.pipeline[2] = bus.fetch16(self.gprs[15] + 4) as u32;
             self.gprs[15] += 4;
         }
     }
@@ -0,0 +1,134 @@
+// EEPROM backup memory (512 bytes or 8KB). It's accessed one bit at a time through bit 0 of halfword accesses
+// to the top of the ROM area, which games do with DMA3. Data is transferred in 64-bit blocks, MSB first
+
+const SMALL_SIZE: usize = 512;
+const LARGE_SIZE: usize = 8 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+enum EEPROMState {
+    Command,      // Waiting for the 2-bit request type (0b11 = read, 0b10 = write)
+    ReadAddress,
+    ReadEnd,      // The 0 bit that ends a read request
+    WriteAddress,
+    WriteData,
+    WriteEnd      // The 0 bit that ends a write request
+}
+
+pub struct EEPROM {
+    pub memory: Vec<u8>,
+    addressBits: u32, // 6 for 512 byte EEPROMs, 14 for 8KB ones. 0 until we find out which one the game uses
+    state: EEPROMState,
+    buffer: u64,      // Bits received so far for the current part of the request
+    bitsReceived: u32,
+    address: usize,   // Offset of the 64-bit block being accessed
+    readData: u64,
+    readBitsLeft: u32 // Read requests are answered with 4 junk bits, then 64 data bits
+}
+
+impl EEPROM {
+    pub fn new() -> EEPROM {
+        EEPROM {
+            memory: vec![0xFF; LARGE_SIZE],
+            addressBits: 0,
+            state: EEPROMState::Command,
+            buffer: 0,
+            bitsReceived: 0,
+            address: 0,
+            readData: 0,
+            readBitsLeft: 0
+        }
+    }
+
+    pub fn setAddressBits(&mut self, addressBits: u32) {
+        self.addressBits = addressBits;
+        self.memory.resize(if addressBits == 6 { SMALL_SIZE } else { LARGE_SIZE }, 0xFF);
+    }
+
+    // The EEPROM size isn't stored anywhere, so figure out the address width from the length of the DMA sending a request.
+    // Read requests are 2 + address + 1 bits long, write requests are 2 + address + 64 + 1 bits long
+    pub fn detectSize(&mut self, wordCount: u32) {
+        if self.addressBits != 0 {
+            return;
+        }
+
+        match wordCount {
+            9 | 73 => self.setAddressBits(6),
+            17 | 81 => self.setAddressBits(14),
+            _ => {}
+        }
+    }
+
+    pub fn read(&mut self) -> u16 {
+        if self.readBitsLeft == 0 { // Not reading, so return the ready bit. Writes complete instantly, so we're always ready
+            return 1;
+        }
+
+        self.readBitsLeft -= 1;
+        if self.readBitsLeft >= 64 { // Junk bits
+            0
+        } else {
+            ((self.readData >> self.readBitsLeft) & 1) as u16
+        }
+    }
+
+    // Returns whether the memory contents changed, which only happens when a write request gets all 64 data bits
+    pub fn write(&mut self, val: u16) -> bool {
+        self.buffer = (self.buffer << 1) | (val & 1) as u64;
+        self.bitsReceived += 1;
+
+        match self.state {
+            EEPROMState::Command if self.bitsReceived == 2 => {
+                self.state = match self.buffer {
+                    0b11 => EEPROMState::ReadAddress,
+                    0b10 => EEPROMState::WriteAddress,
+                    _ => EEPROMState::Command
+                };
+                self.resetBuffer();
+            }
+
+            EEPROMState::ReadAddress | EEPROMState::WriteAddress => {
+                if self.addressBits == 0 {
+                    println!("EEPROM accessed before its size was detected, assuming 8KB");
+                    self.setAddressBits(14);
+                }
+
+                if self.bitsReceived == self.addressBits {
+                    let blocks = self.memory.len() / 8;
+                    self.address = (self.buffer as usize & (blocks - 1)) * 8;
+                    self.state = if self.state == EEPROMState::ReadAddress { EEPROMState::ReadEnd } else { EEPROMState::WriteData };
+                    self.resetBuffer();
+                }
+            }
+
+            EEPROMState::ReadEnd => {
+                let mut block = [0; 8];
+                block.copy_from_slice(&self.memory[self.address..self.address + 8]);
+                self.readData = u64::from_be_bytes(block);
+                self.readBitsLeft = 68;
+                self.state = EEPROMState::Command;
+                self.resetBuffer();
+            }
+
+            EEPROMState::WriteData if self.bitsReceived == 64 => {
+                self.memory[self.address..self.address + 8].copy_from_slice(&self.buffer.to_be_bytes());
+                self.state = EEPROMState::WriteEnd;
+                self.resetBuffer();
+                return true;
+            }
+
+            EEPROMState::WriteEnd => {
+                self.state = EEPROMState::Command;
+                self.resetBuffer();
+            }
+
+            _ => {}
+        }
+
+        false
+    }
+
+    fn resetBuffer(&mut self) {
+        self.buffer = 0;
+        self.bitsReceived = 0;
+    }
+}
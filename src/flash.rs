@@ -0,0 +1,216 @@
+// Flash backup memory. Commands are sent by writing to 0x0E005555 and 0x0E002AAA in a specific sequence,
+// and 128KB chips are accessed as 2 banks of 64KB
+
+const BANK_SIZE: usize = 64 * 1024;
+const SECTOR_SIZE: usize = 4 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlashManufacturer {
+    Macronix,
+    Panasonic,
+    Sanyo,
+    SST
+}
+
+impl FlashManufacturer {
+    // Real 64KB carts use Panasonic chips and 128KB carts use Sanyo ones, so games expect those unless told otherwise
+    pub fn default(is128K: bool) -> FlashManufacturer {
+        if is128K { FlashManufacturer::Sanyo } else { FlashManufacturer::Panasonic }
+    }
+
+    pub fn fromName(name: &str) -> Option<FlashManufacturer> {
+        match name.to_lowercase().as_str() {
+            "macronix" => Some(FlashManufacturer::Macronix),
+            "panasonic" => Some(FlashManufacturer::Panasonic),
+            "sanyo" => Some(FlashManufacturer::Sanyo),
+            "sst" => Some(FlashManufacturer::SST),
+            _ => None
+        }
+    }
+
+    // Manufacturer and device ID returned in chip ID mode
+    pub fn getID(self, is128K: bool) -> (u8, u8) {
+        match (self, is128K) {
+            (FlashManufacturer::Macronix, false)  => (0xC2, 0x1C),
+            (FlashManufacturer::Macronix, true)   => (0xC2, 0x09),
+            (FlashManufacturer::Panasonic, _)     => (0x32, 0x1B),
+            (FlashManufacturer::Sanyo, _)         => (0x62, 0x13),
+            (FlashManufacturer::SST, _)           => (0xBF, 0xD4)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FlashState {
+    Ready,
+    Unlock1,    // Got 0xAA at 0x5555
+    Unlock2,    // Got 0x55 at 0x2AAA, waiting for a command
+    Program,    // The next write programs a byte
+    BankSwitch  // The next write to 0x0000 selects the bank
+}
+
+pub struct Flash {
+    pub memory: Vec<u8>,
+    pub manufacturer: FlashManufacturer,
+    state: FlashState,
+    idMode: bool,    // Reads from 0x0000/0x0001 return the chip ID instead of memory
+    eraseMode: bool, // Got the erase command (0x80), so the next command is a chip or sector erase
+    bank: usize
+}
+
+impl Flash {
+    pub fn new(is128K: bool, manufacturer: FlashManufacturer) -> Flash {
+        let size = if is128K { BANK_SIZE * 2 } else { BANK_SIZE };
+
+        Flash {
+            memory: vec![0xFF; size],
+            manufacturer,
+            state: FlashState::Ready,
+            idMode: false,
+            eraseMode: false,
+            bank: 0
+        }
+    }
+
+    pub fn is128K(&self) -> bool {
+        self.memory.len() > BANK_SIZE
+    }
+
+    pub fn read(&self, address: u32) -> u8 {
+        let address = address as usize & 0xFFFF;
+
+        if self.idMode && address < 2 {
+            let (manufacturerID, deviceID) = self.manufacturer.getID(self.is128K());
+            return if address == 0 { manufacturerID } else { deviceID };
+        }
+
+        self.memory[self.bank * BANK_SIZE + address]
+    }
+
+    // Returns whether the memory contents changed. Commands and unlock writes don't change them
+    pub fn write(&mut self, address: u32, val: u8) -> bool {
+        let address = address as usize & 0xFFFF;
+
+        match self.state {
+            FlashState::Program => {
+                let byte = &mut self.memory[self.bank * BANK_SIZE + address];
+                let changed = *byte != val;
+                *byte = val;
+                self.state = FlashState::Ready;
+                return changed;
+            }
+
+            FlashState::BankSwitch => {
+                if address == 0 {
+                    self.bank = val as usize & 1;
+                }
+
+                self.state = FlashState::Ready;
+            }
+
+            FlashState::Ready => {
+                if address == 0x5555 && val == 0xAA {
+                    self.state = FlashState::Unlock1;
+                }
+
+                else if val == 0xF0 { // Reset to read mode. Some chips accept this without the unlock sequence
+                    self.idMode = false;
+                    self.eraseMode = false;
+                }
+            }
+
+            FlashState::Unlock1 => {
+                self.state = if address == 0x2AAA && val == 0x55 { FlashState::Unlock2 } else { FlashState::Ready };
+            }
+
+            FlashState::Unlock2 => {
+                self.state = FlashState::Ready;
+
+                if self.eraseMode {
+                    self.eraseMode = false;
+                    match (address, val) {
+                        (0x5555, 0x10) => { // Chip erase
+                            self.memory.iter_mut().for_each(|byte| *byte = 0xFF);
+                            return true;
+                        }
+
+                        (_, 0x30) => { // Sector erase
+                            let start = self.bank * BANK_SIZE + (address & !(SECTOR_SIZE - 1));
+                            self.memory[start..start + SECTOR_SIZE].iter_mut().for_each(|byte| *byte = 0xFF);
+                            return true;
+                        }
+
+                        _ => println!("Unknown Flash erase command {:02X} at {:04X}", val, address)
+                    }
+                }
+
+                else if address == 0x5555 {
+                    match val {
+                        0x90 => self.idMode = true,
+                        0xF0 => self.idMode = false,
+                        0x80 => self.eraseMode = true,
+                        0xA0 => self.state = FlashState::Program,
+                        0xB0 if self.is128K() => self.state = FlashState::BankSwitch,
+                        _ => println!("Unknown Flash command {:02X}", val)
+                    }
+                }
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{Backup, save_types};
+
+    fn readChipID(backup: &mut Backup) -> (u8, u8) {
+        backup.write8(0x0E005555, 0xAA); // Enter ID mode
+        backup.write8(0x0E002AAA, 0x55);
+        backup.write8(0x0E005555, 0x90);
+        (backup.read8(0x0E000000), backup.read8(0x0E000001))
+    }
+
+    // Returns whether any of the writes changed the Flash contents
+    fn sendCommand(backup: &mut Backup, cmd: u8) -> bool {
+        let unlock1 = backup.write8(0x0E005555, 0xAA);
+        let unlock2 = backup.write8(0x0E002AAA, 0x55);
+        unlock1 | unlock2 | backup.write8(0x0E005555, cmd)
+    }
+
+    #[test]
+    fn chip_id_for_each_manufacturer() {
+        let chips = [
+            (FlashManufacturer::Macronix, save_types::Flash64K, (0xC2, 0x1C)),
+            (FlashManufacturer::Macronix, save_types::Flash128K, (0xC2, 0x09)),
+            (FlashManufacturer::Panasonic, save_types::Flash64K, (0x32, 0x1B)),
+            (FlashManufacturer::Sanyo, save_types::Flash128K, (0x62, 0x13)),
+            (FlashManufacturer::SST, save_types::Flash64K, (0xBF, 0xD4))
+        ];
+
+        for &(manufacturer, saveType, id) in chips.iter() {
+            assert_eq!(readChipID(&mut Backup::new(saveType, Some(manufacturer))), id, "{:?}", manufacturer);
+        }
+
+        assert_eq!(readChipID(&mut Backup::new(save_types::Flash64K, None)), (0x32, 0x1B)); // Defaults
+        assert_eq!(readChipID(&mut Backup::new(save_types::Flash128K, None)), (0x62, 0x13));
+    }
+
+    #[test]
+    fn only_data_writes_change_memory() {
+        let mut backup = Backup::new(save_types::Flash64K, None);
+
+        assert!(!sendCommand(&mut backup, 0x90)); // Enter and leave ID mode
+        assert!(!sendCommand(&mut backup, 0xF0));
+        assert!(!sendCommand(&mut backup, 0xA0));
+        assert!(backup.write8(0x0E001234, 0x12)); // Program a byte
+
+        assert!(!sendCommand(&mut backup, 0xA0)); // Programming the same value doesn't change anything
+        assert!(!backup.write8(0x0E001234, 0x12));
+
+        assert!(!sendCommand(&mut backup, 0x80));
+        assert!(sendCommand(&mut backup, 0x10)); // Chip erase
+    }
+}
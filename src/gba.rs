@@ -1,47 +1,88 @@
 use crate::cpu::CPU;
 use crate::bus::Bus;
+use crate::mem::Memory;
+use crate::loader::CARTRIDGE_START;
 use crate::DMA::DMAChannelStatus;
-use std::time::Instant;
-
-use sfml::graphics::*;
-use sfml::system::*;
 use crate::scheduler::*;
-use std::slice;
+use crate::cartridge::{save_types, CartridgeHeader};
+use crate::rtc::{RTC, TimeSource};
+use crate::cheats::{Cheat, CheatDevice};
+use crate::flash::FlashManufacturer;
+use std::path::Path;
+
+const SAVE_FLUSH_INTERVAL: u32 = 60; // Frames between a write to backup memory and flushing it to the .sav file
+
+// How to boot a ROM
+#[derive(Clone, Debug, Default)]
+pub struct GBAOptions {
+    pub biosPath: Option<String>, // BIOS image to boot with. If None, SWIs are handled natively by the HLE BIOS
+    pub directBoot: bool,         // Skip the BIOS intro and boot straight into the ROM
+    pub patchPath: Option<String>, // IPS/UPS/BPS patch to apply to the ROM. If None, one next to the ROM with the same name is used
+    pub flashManufacturer: Option<FlashManufacturer> // Chip ID reported by Flash backup memory. If None, Panasonic for 64KB and Sanyo for 128KB
+}
 
 pub struct GBA {
     cpu: CPU,
     bus: Bus,
     isFrameReady: bool,
-    texture: SfBox<Texture>
+    directBoot: bool, // Skip the BIOS intro and boot straight into the ROM
+    framesSinceDirty: u32 // Frames since backup memory was first modified without being flushed
 }
 
 impl GBA {
-    pub fn new(romPath: String) -> GBA {
+    // romPath can be a ROM or a .zip archive containing one. Cheats are loaded from the .cht file next to it, if there is one
+    pub fn new(romPath: &str, options: GBAOptions) -> Result<GBA, String> {
+        let cheatPath = Path::new(romPath).with_extension("cht");
+        let mut gba = GBA::withMemory(Memory::new(romPath, &options)?, options.directBoot);
+
+        if cheatPath.exists() {
+            if let Err(e) = gba.bus.cheats.loadFile(&cheatPath) {
+                println!("Failed to load cheats from {}: {}", cheatPath.display(), e);
+            }
+        }
+
+        Ok(gba)
+    }
+
+    // Create a GBA without touching the filesystem. Without a BIOS image, the HLE BIOS is used.
+    // Backup memory isn't saved anywhere
+    pub fn from_bytes(rom: Vec<u8>, bios: Option<Vec<u8>>) -> Result<GBA, String> {
+        Ok(GBA::withMemory(Memory::fromBytes(rom, bios, None)?, false))
+    }
+
+    fn withMemory(mem: Memory, directBoot: bool) -> GBA {
+        let directBoot = directBoot || mem.entryPoint != CARTRIDGE_START; // The BIOS only knows how to boot cartridges
         GBA {
             cpu: CPU::new(),
-            bus: Bus::new(romPath),
+            bus: Bus::new(mem),
             isFrameReady: false,
-            texture: Texture::new(240, 160).unwrap()
+            directBoot,
+            framesSinceDirty: 0
         }
     }
 
     pub fn init(&mut self) {
-        self.cpu.init(&self.bus);
+        self.cpu.init(&mut self.bus, self.directBoot);
         self.bus.scheduler.pushEvent(EventTypes::HBlank, 960); // Add first HBlank event to the scheduler    
     }
 
     pub fn step(&mut self) {
         if !self.bus.halted { // Check HALTCNT
-            self.advanceScheduler(2);
             self.cpu.step(&mut self.bus);
+            let cycles = self.bus.takePendingCycles(); // The instruction's memory accesses and internal cycles
+            self.advanceScheduler(cycles);
         }
 
-        else {
-            self.advanceScheduler(2);
+        else { // While halted, nothing happens until the next event, so skip straight to it
+            let cycles = self.bus.scheduler.getNearestEvent().endTimestamp.saturating_sub(self.bus.scheduler.currentTimestamp);
+            let pendingCycles = self.bus.takePendingCycles(); // Cycles left over from DMAs fired during the halt
+            self.bus.runPrefetcher(cycles as u32); // The Game Pak bus is idle, so the prefetcher keeps going
+            self.advanceScheduler(cycles.max(pendingCycles));
         }
     }
 
-    pub fn executeFrame (&mut self, window: &mut sfml::graphics::RenderWindow) {
+    // Run the emulator until the PPU enters VBlank
+    pub fn run_frame (&mut self) {
         self.isFrameReady = false;
         //let start = Instant::now(); // Start time of the frame
         
@@ -49,34 +90,110 @@ impl GBA {
             self.step();
         }
 
-        self.bus.joypad.update(); // Update joypad
+        self.bus.updateGPIO();
+        self.bus.runCheats();
 
-        // poll window events and render screen
-        while let Some(event) = window.poll_event() {
-            if event == sfml::window::Event::Closed {
-                //println!("Writing CPU log to disk\n");
-                //let mut file = File::create("CPULog.txt").unwrap();
-                //file.write_all(self.cpu.log.as_bytes());
-                std::process::exit(0);
+        if self.bus.mem.isBackupDirty {
+            self.framesSinceDirty += 1;
+            if self.framesSinceDirty >= SAVE_FLUSH_INTERVAL {
+                self.flush_save();
             }
         }
-    /*
-        if sfml::window::Key::is_pressed(sfml::window::Key::D) {
-            self.cpu.logState();
-        }
-    */
+
         //println!("Frame time: {}ms", start.elapsed().as_millis());  
         //println!("FPS: {}", 16.0 / start.elapsed().as_millis() as f64 * 60.0);
+    }
+
+    // The last rendered frame, as 240x160 RGBA8888 pixels
+    pub fn framebuffer (&self) -> &[u8] {
+        &self.bus.ppu.pixels
+    }
+
+    // Set which buttons are held. Bits follow KEYINPUT order (A, B, Select, Start, Right, Left, Up, Down, R, L), 1 = pressed
+    pub fn set_keys (&mut self, pressedKeys: u16) {
+        self.bus.joypad.setKeys(pressedKeys);
+    }
+
+    pub fn header (&self) -> &CartridgeHeader {
+        &self.bus.mem.header
+    }
+
+    // Override the save type detected from the ROM. Call this before init
+    pub fn set_save_type (&mut self, saveType: save_types) {
+        self.bus.mem.setSaveType(saveType);
+    }
+
+    // Change where the cartridge RTC gets its time from, eg a fixed time for reproducible runs.
+    // This also adds an RTC to games that weren't detected as having one
+    pub fn set_rtc_time_source (&mut self, timeSource: TimeSource) {
+        match &mut self.bus.gpio.rtc {
+            Some(rtc) => rtc.timeSource = timeSource,
+            None => self.bus.gpio.rtc = Some(RTC::new(timeSource))
+        }
+    }
 
-        let sprite: Sprite;
-        unsafe {
-            self.texture.update_from_pixels(&self.bus.ppu.pixels, 240, 160, 0, 0);
-            sprite = Sprite::with_texture(&self.texture);
+    // Set how much light the Boktai solar sensor sees, from 0 (darkness) to 255 (direct sunlight).
+    // Does nothing for cartridges without one
+    pub fn set_solar_level (&mut self, level: u8) {
+        if let Some(solar) = &mut self.bus.gpio.solar {
+            solar.lightLevel = level;
         }
-    
-        // It's not necessary to clear the window since we're redrawing the whole thing anyways
-        window.draw(&sprite);
-        window.display();
+    }
+
+    // Tilt the cartridge's accelerometer. Both axes go from -1.0 to 1.0, with positive values meaning right and down.
+    // Does nothing for cartridges without one
+    pub fn set_tilt (&mut self, x: f32, y: f32) {
+        if let Some(tilt) = &mut self.bus.tilt {
+            tilt.x = x;
+            tilt.y = y;
+        }
+    }
+
+    // Set the rotation speed seen by the cartridge's gyroscope, from -1.0 (counterclockwise) to 1.0 (clockwise).
+    // Does nothing for cartridges without one
+    pub fn set_gyro (&mut self, rotation: f32) {
+        if let Some(gyro) = &mut self.bus.gpio.gyro {
+            gyro.rotation = rotation;
+        }
+    }
+
+    // The rumble motor state (true = on), if it changed since the last call. Games can toggle the motor many times a frame,
+    // so only the latest state is kept
+    pub fn take_rumble_change (&mut self) -> Option<bool> {
+        let gpio = &mut self.bus.gpio;
+        if std::mem::take(&mut gpio.rumbleChanged) { Some(gpio.isRumbling) } else { None }
+    }
+
+    // Load cheats from a cheat file, adding them to the ones already loaded. Returns how many were loaded
+    pub fn load_cheats (&mut self, path: &str) -> Result<usize, String> {
+        if !Path::new(path).is_file() {
+            return Err(format!("{} is not a file", path));
+        }
+
+        self.bus.cheats.loadFile(Path::new(path))
+    }
+
+    // Add a cheat from its codes, one per line. Cheats start enabled
+    pub fn add_cheat (&mut self, name: &str, device: CheatDevice, codes: &str) -> Result<(), String> {
+        self.bus.cheats.list.push(Cheat::new(name, device, codes)?);
+        Ok(())
+    }
+
+    pub fn cheats (&self) -> &[Cheat] {
+        &self.bus.cheats.list
+    }
+
+    // Turn a cheat on or off. Takes effect at the end of the current frame
+    pub fn set_cheat_enabled (&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.bus.cheats.list.get_mut(index) {
+            cheat.enabled = enabled;
+        }
+    }
+
+    // Write backup memory to the .sav file next to the ROM, if it has changed. GBAs created from_bytes have no .sav file
+    pub fn flush_save (&mut self) {
+        self.bus.mem.flushSave();
+        self.framesSinceDirty = 0;
     }
 
     fn advanceScheduler(&mut self, cycles: u64) {
@@ -106,7 +223,7 @@ impl GBA {
 
                 if self.bus.ppu.vcount < 160 {
                     self.bus.ppu.renderScanline();
-                    //self.bus.ppu.incrementAffineRegs();
+                    self.bus.ppu.incrementAffineRegs();
                     self.bus.pollDMAs(DMAChannelStatus::HBlank); // See if there's any HBlank-triggered DMAs to fire. HBlank DMAs DO NOT fire during VBlank
                 }
 
@@ -121,6 +238,7 @@ impl GBA {
                 if self.bus.ppu.vcount == 160 { // If PPU is entering VBlank, run VBlank events
                     self.isFrameReady = true;
                     self.bus.ppu.dispstat.setVBlankFlag(1);
+                    self.bus.ppu.latchAffineRegs(); // The internal affine reference points get reloaded every VBlank
 
                     if self.bus.ppu.dispstat.getVBlankIRQEnable() == 1 {
                         self.bus.ppu.interruptFlags |= 1;
@@ -150,4 +268,10 @@ impl GBA {
             _ => panic!("unknown event!")
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for GBA {
+    fn drop(&mut self) {
+        self.flush_save();
+    }
+}
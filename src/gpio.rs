@@ -0,0 +1,157 @@
+use crate::bus::Bus;
+use crate::rtc::{RTC, TimeSource};
+use crate::solar::SolarSensor;
+use crate::gyro::GyroSensor;
+use crate::scheduler::EventTypes;
+use crate::cartridge::CartridgeHeader;
+use crate::isBitSet;
+
+// Cartridge GPIO port, used by carts with extra hardware (RTC, sensors, rumble).
+// It's mapped over the ROM header at 0x080000C4-0x080000C9
+pub const GPIO_DATA: u32 = 0x080000C4;      // The 4 pins
+pub const GPIO_DIRECTION: u32 = 0x080000C6; // 1 = pin is an output from the GBA, 0 = input from the device
+pub const GPIO_CONTROL: u32 = 0x080000C8;   // Bit 0: Make the GPIO registers readable. Otherwise they read as ROM
+
+const SOLAR_SENSOR_GAMES: [&str; 3] = ["U3I", "U32", "U33"]; // Boktai 1, 2 and 3 (all regions)
+const GYRO_GAMES: [&str; 1] = ["RZW"]; // WarioWare: Twisted
+const RUMBLE_GAMES: [&str; 2] = ["RZW", "V49"]; // WarioWare: Twisted, Drill Dozer
+const RUMBLE_PIN: u8 = 3;
+
+pub struct Gpio {
+    data: u8,
+    direction: u8,
+    readable: bool,
+    pub rtc: Option<RTC>,
+    pub solar: Option<SolarSensor>,
+    pub gyro: Option<GyroSensor>,
+    hasRumble: bool,
+    pub isRumbling: bool,
+    pub rumbleChanged: bool // Whether the rumble motor turned on or off since the frontend last checked
+}
+
+impl Gpio {
+    // Games using Nintendo's RTC library contain its ID string. Other devices are detected from the game code in the header
+    pub fn new(rom: &[u8], header: &CartridgeHeader) -> Gpio {
+        let gameCode = &header.gameCode;
+        let hasSolarSensor = SOLAR_SENSOR_GAMES.iter().any(|code| gameCode.starts_with(code));
+        let hasRTC = hasSolarSensor || rom.windows(8).any(|window| window == b"SIIRTC_V");
+        let hasGyro = GYRO_GAMES.iter().any(|code| gameCode.starts_with(code));
+        let hasRumble = RUMBLE_GAMES.iter().any(|code| gameCode.starts_with(code));
+
+        Gpio {
+            data: 0,
+            direction: 0,
+            readable: false,
+            rtc: if hasRTC { Some(RTC::new(TimeSource::Host)) } else { None },
+            solar: if hasSolarSensor { Some(SolarSensor::new()) } else { None },
+            gyro: if hasGyro { Some(GyroSensor::new()) } else { None },
+            hasRumble,
+            isRumbling: false,
+            rumbleChanged: false
+        }
+    }
+
+    pub fn isConnected(&self) -> bool {
+        self.rtc.is_some() || self.solar.is_some() || self.gyro.is_some() || self.hasRumble
+    }
+
+    // Pins driven by the devices
+    fn readDevicePins(&self) -> u8 {
+        let mut pins = 0;
+        if let Some(rtc) = &self.rtc {
+            pins |= rtc.readPins();
+        }
+
+        if let Some(solar) = &self.solar {
+            pins |= solar.readPins();
+        }
+
+        if let Some(gyro) = &self.gyro {
+            pins |= gyro.readPins();
+        }
+
+        pins
+    }
+
+    pub fn read(&self, address: u32) -> u16 {
+        match address & !1 {
+            GPIO_DATA => ((self.data & self.direction) | (self.readDevicePins() & !self.direction)) as u16 & 0xF,
+            GPIO_DIRECTION => self.direction as u16,
+            _ => self.readable as u16
+        }
+    }
+
+    pub fn write(&mut self, address: u32, val: u16) {
+        match address & !1 {
+            GPIO_DATA => {
+                self.data = val as u8 & 0xF;
+                let pins = (self.data & self.direction) | (self.readDevicePins() & !self.direction);
+
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.writePins(pins);
+                }
+
+                if let Some(solar) = &mut self.solar {
+                    solar.writePins(pins);
+                }
+
+                if let Some(gyro) = &mut self.gyro {
+                    gyro.writePins(pins);
+                }
+
+                let isRumbling = isBitSet!(pins, RUMBLE_PIN);
+                if self.hasRumble && isRumbling != self.isRumbling {
+                    self.isRumbling = isRumbling;
+                    self.rumbleChanged = true;
+                }
+            }
+
+            GPIO_DIRECTION => self.direction = val as u8 & 0xF,
+            _ => self.readable = isBitSet!(val, 0)
+        }
+    }
+
+    // Whether a device pulled the cartridge IRQ line
+    fn takeIRQ(&mut self) -> bool {
+        let mut irq = false;
+        if let Some(rtc) = &mut self.rtc {
+            irq |= rtc.irqRequested;
+            rtc.irqRequested = false;
+        }
+
+        irq
+    }
+}
+
+impl Bus {
+    pub fn isGPIOAccess(&self, address: u32) -> bool {
+        self.gpio.isConnected() && (GPIO_DATA..GPIO_CONTROL + 2).contains(&address)
+    }
+
+    // Reads return ROM data unless the game made the registers readable
+    pub fn isGPIORead(&self, address: u32) -> bool {
+        self.isGPIOAccess(address) && self.gpio.readable
+    }
+
+    pub fn writeGPIO(&mut self, address: u32, val: u16) {
+        self.gpio.write(address, val);
+        self.pollGamePakIRQ();
+    }
+
+    // Devices on the cartridge can request a Game Pak interrupt
+    pub fn pollGamePakIRQ(&mut self) {
+        if self.gpio.takeIRQ() {
+            self.gamepak_irq_request = true;
+            self.scheduler.pushEvent(EventTypes::PollInterrupts, 0);
+        }
+    }
+
+    // Time-based device updates. Called once per frame
+    pub fn updateGPIO(&mut self) {
+        if let Some(rtc) = &mut self.gpio.rtc {
+            rtc.update();
+        }
+
+        self.pollGamePakIRQ();
+    }
+}
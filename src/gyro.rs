@@ -0,0 +1,44 @@
+use crate::isBitSet;
+
+// Gyroscope used by WarioWare: Twisted, connected to the cartridge GPIO port.
+// Setting pin 0 makes the ADC sample the rotation speed, and the sample is then shifted out MSB first on pin 2,
+// one bit on each falling edge of the clock on pin 1
+
+const CENTER: i32 = 0x6C0; // Reading when the GBA isn't rotating
+const RANGE: f32 = 0x400 as f32; // Reading difference at maximum rotation speed
+
+pub struct GyroSensor {
+    pub rotation: f32, // Rotation speed set by the host, from -1.0 (counterclockwise) to 1.0 (clockwise)
+    sample: u16,
+    clock: bool,   // Previous state of the clock pin, to detect falling edges
+    dataOut: bool
+}
+
+impl GyroSensor {
+    pub fn new() -> GyroSensor {
+        GyroSensor {
+            rotation: 0.0,
+            sample: 0,
+            clock: false,
+            dataOut: false
+        }
+    }
+
+    pub fn readPins(&self) -> u8 {
+        (self.dataOut as u8) << 2
+    }
+
+    pub fn writePins(&mut self, pins: u8) {
+        if isBitSet!(pins, 0) {
+            self.sample = (CENTER + (self.rotation.max(-1.0).min(1.0) * RANGE) as i32) as u16;
+        }
+
+        let clock = isBitSet!(pins, 1);
+        if self.clock && !clock {
+            self.dataOut = isBitSet!(self.sample, 15);
+            self.sample <<= 1;
+        }
+
+        self.clock = clock;
+    }
+}
@@ -1,6 +1,3 @@
-use std::fs::File;
-use std::io::Read;
-
 #[macro_export]
 macro_rules! isBitSet {
     ($num:expr, $bit:expr) => {
@@ -32,13 +29,8 @@ macro_rules! sign_extend_16 {
   }
 }
 
-pub fn readFileIntoVec(filename: &String) -> Vec<u8> {
-    let mut f = File::open(&filename).expect("no file found");
-    let metadata = std::fs::metadata(&filename).expect("unable to read metadata");
-    let mut buffer = vec![0; metadata.len() as usize];
-    f.read(&mut buffer).expect("buffer overflow");
-    
-    buffer
+pub fn readFileIntoVec(filename: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(filename).map_err(|e| format!("Couldn't read {}: {}", filename, e))
 }
 
 pub const fn get8BitColor (colorToConvert: u8) -> u8 { // Extend a 5-bit color value into an 8-bit color value
@@ -1,7 +1,4 @@
-extern crate sfml;
 use crate::io::KEYINPUT;
-use sfml::window::Key;
-const KEYS: [Key; 10] = [Key::A, Key::S, Key::BackSpace, Key::Return, Key::Right, Key::Left, Key::Up, Key::Down, Key::R, Key::L];
 
 pub struct Joypad {
     pub keyinput: KEYINPUT
@@ -14,14 +11,7 @@ impl Joypad {
         }
     }
 
-    pub fn update(&mut self) {
-        let mut newKeyinput = 0_u16;
-        for i in 0..10 {
-            if !Key::is_pressed(KEYS[i]) {
-                newKeyinput |= 1 << i;
-            }
-        }
-
-        self.keyinput.setRaw(newKeyinput);
+    pub fn setKeys(&mut self, pressedKeys: u16) { // KEYINPUT is active low, so a pressed key reads as 0
+        self.keyinput.setRaw(!pressedKeys & 0x3FF);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,43 @@
+#![warn(clippy::all)]
+#![allow(nonstandard_style)]
+#![feature(const_fn)]
+#![feature(const_panic)]
+
+// The emulator core. Nothing in here knows about windows, textures or host input,
+// so it can be driven headlessly (CI, tools) or by any frontend through the GBA struct.
+
+extern crate staticvec;
+
+pub mod gba;
+pub mod bus;
+pub mod cpu;
+pub mod mem;
+pub mod DMA;
+pub mod PPU;
+pub mod ARM;
+pub mod irqs;
+pub mod io;
+pub mod timers;
+pub mod joypad;
+pub mod cartridge;
+pub mod flash;
+pub mod eeprom;
+pub mod gpio;
+pub mod rtc;
+pub mod solar;
+pub mod tilt;
+pub mod gyro;
+pub mod patch;
+pub mod cheats;
+pub mod config;
+pub mod loader;
+pub mod Thumb;
+pub mod BIOS;
+pub mod barrelShifter;
+pub mod helpers;
+pub mod scheduler;
+pub mod waitstates;
+pub mod prefetch;
+
+pub use gba::{GBA, GBAOptions};
+pub use crate::PPU::ppu::{WIDTH, HEIGHT};
@@ -0,0 +1,126 @@
+use crate::helpers::readFileIntoVec;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ROM_EXTENSIONS: [&str; 3] = ["gba", "agb", "bin"];
+
+// Read a ROM file. Zip archives are detected from their contents, and the first ROM inside them is used
+pub fn readROM(path: &str) -> Result<Vec<u8>, String> {
+    let data = readFileIntoVec(path)?;
+    if data.starts_with(ZIP_MAGIC) {
+        extractROM(data).map_err(|e| format!("Couldn't read {}: {}", path, e))
+    } else {
+        Ok(data)
+    }
+}
+
+fn isROMName(name: &str) -> bool {
+    matches!(Path::new(name).extension(), Some(ext) if ROM_EXTENSIONS.iter().any(|romExt| ext.eq_ignore_ascii_case(romExt)))
+}
+
+// Unzip the first .gba/.agb/.bin file in the archive
+fn extractROM(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| e.to_string())?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        if file.is_file() && isROMName(file.name()) {
+            let mut rom = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut rom).map_err(|e| format!("Couldn't extract {}: {}", file.name(), e))?;
+            return Ok(rom);
+        }
+    }
+
+    Err("Archive doesn't contain a .gba, .agb or .bin file".to_string())
+}
+
+const ELF_MAGIC: &[u8] = b"\x7FELF";
+const PT_LOAD: u32 = 1;           // Program header type for segments that get loaded into memory
+const EM_ARM: u16 = 40;
+pub const CARTRIDGE_START: u32 = 0x08000000;
+pub const MULTIBOOT_START: u32 = 0x02000000; // Multiboot images are loaded to the start of EWRAM
+const MULTIBOOT_MAX_SIZE: usize = 256 * 1024;
+
+// Data loaded straight into RAM
+pub struct Segment {
+    pub address: u32,
+    pub data: Vec<u8>
+}
+
+// What to put in memory before booting
+pub struct Program {
+    pub rom: Vec<u8>,           // Cartridge ROM, mapped at 0x08000000. Empty for programs that run from RAM without a cartridge
+    pub segments: Vec<Segment>,
+    pub entryPoint: u32         // Where execution starts when the BIOS is skipped
+}
+
+impl Program {
+    pub fn cartridge(rom: Vec<u8>) -> Program {
+        Program { rom, segments: vec![], entryPoint: CARTRIDGE_START }
+    }
+
+    pub fn multiboot(image: Vec<u8>) -> Result<Program, String> {
+        if image.len() > MULTIBOOT_MAX_SIZE {
+            return Err(format!("Multiboot image is {} bytes long, but it has to fit in the 256KB of EWRAM", image.len()));
+        }
+
+        Ok(Program { rom: vec![], segments: vec![Segment { address: MULTIBOOT_START, data: image }], entryPoint: MULTIBOOT_START })
+    }
+
+    // Load the segments from the program headers of an ELF file. Segments are loaded at their physical address,
+    // so initialized data that the startup code copies out of ROM ends up in ROM
+    pub fn elf(data: &[u8]) -> Result<Program, String> {
+        let read16 = |offset: usize| data.get(offset..offset + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]])).ok_or("ELF file is truncated");
+        let read32 = |offset: usize| data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).ok_or("ELF file is truncated");
+
+        if data.get(4) != Some(&1) || data.get(5) != Some(&1) {
+            return Err("Only 32-bit little endian ELF files are supported".to_string());
+        }
+
+        if read16(0x12)? != EM_ARM {
+            return Err("ELF file isn't for ARM".to_string());
+        }
+
+        let entryPoint = read32(0x18)?;
+        let headerOffset = read32(0x1C)? as usize;
+        let headerSize = read16(0x2A)? as usize;
+        let headerCount = read16(0x2C)? as usize;
+        let mut program = Program { rom: vec![], segments: vec![], entryPoint };
+
+        for i in 0..headerCount {
+            let header = headerOffset + i * headerSize;
+            let (offset, address, fileSize) = (read32(header + 0x4)? as usize, read32(header + 0xC)?, read32(header + 0x10)? as usize);
+            if read32(header)? != PT_LOAD || fileSize == 0 { // .bss is cleared by the startup code
+                continue;
+            }
+
+            let segment = data.get(offset..offset + fileSize).ok_or("ELF segment is outside of the file")?;
+            if (0x8..=0xD).contains(&(address >> 24)) { // ROM is mirrored in all 3 wait state regions
+                let romOffset = (address & 0x1FFFFFF) as usize;
+                if program.rom.len() < romOffset + fileSize {
+                    program.rom.resize(romOffset + fileSize, 0);
+                }
+
+                program.rom[romOffset..romOffset + fileSize].copy_from_slice(segment);
+            } else {
+                program.segments.push(Segment { address, data: segment.to_vec() });
+            }
+        }
+
+        Ok(program)
+    }
+
+    // Work out what kind of program a file is. ELF files are detected from their contents, multiboot images from the .mb extension
+    pub fn parse(path: &str, data: Vec<u8>) -> Result<Program, String> {
+        let isMultiboot = matches!(Path::new(path).extension(), Some(ext) if ext.eq_ignore_ascii_case("mb"));
+
+        if data.starts_with(ELF_MAGIC) {
+            Program::elf(&data)
+        } else if isMultiboot {
+            Program::multiboot(data)
+        } else {
+            Ok(Program::cartridge(data))
+        }
+    }
+}
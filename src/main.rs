@@ -1,45 +1,154 @@
 #![warn(clippy::all)]
 #![allow(nonstandard_style)]
-#![feature(const_fn)]
-#![feature(const_panic)]
 
 extern crate sfml;
-extern  crate staticvec;
-
-pub mod gba;
-pub mod bus;
-pub mod cpu;
-pub mod mem;
-pub mod DMA;
-pub mod PPU;
-pub mod ARM;
-pub mod irqs;
-pub mod io;
-pub mod timers;
-pub mod joypad;
-pub mod cartridge;
-pub mod Thumb;
-pub mod barrelShifter;
-pub mod helpers;
-pub mod scheduler;
-
-use gba::GBA;
+
+use hewwo::{GBA, GBAOptions, WIDTH, HEIGHT};
+use hewwo::cartridge::save_types;
+use hewwo::flash::FlashManufacturer;
+use hewwo::config::{Config, DEFAULT_CONFIG_PATH};
+use std::path::Path;
 use sfml::graphics::*;
 use sfml::window::*; // TODO: Not import the entire thing
 
+// Host keys for A, B, Select, Start, Right, Left, Up, Down, R, L (same order as KEYINPUT)
+const KEYS: [Key; 10] = [Key::A, Key::S, Key::BackSpace, Key::Return, Key::Right, Key::Left, Key::Up, Key::Down, Key::R, Key::L];
+
+fn pollKeys() -> u16 {
+    let mut pressedKeys = 0_u16;
+    for i in 0..10 {
+        if Key::is_pressed(KEYS[i]) {
+            pressedKeys |= 1 << i;
+        }
+    }
+
+    pressedKeys
+}
+
+// Toggle the first 12 cheats
+const CHEAT_KEYS: [Key; 12] = [Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12];
+
+fn axis(negative: Key, positive: Key) -> f32 {
+    (Key::is_pressed(positive) as i32 - Key::is_pressed(negative) as i32) as f32
+}
+
+// Tilt sensor axes, controlled with IJKL
+fn pollTilt() -> (f32, f32) {
+    (axis(Key::J, Key::L), axis(Key::I, Key::K))
+}
+
+const USAGE: &str = "Usage: beeg <ROM path> [--bios=PATH | --hle-bios] [--direct-boot] [--config=PATH] [--patch=PATH] [--cheats=PATH] [--save-type=TYPE] [--flash-manufacturer=NAME]";
+
+// Value of a --name=value argument
+fn option(name: &str) -> Option<String> {
+    let prefix = format!("--{}=", name);
+    std::env::args().find_map(|arg| arg.strip_prefix(&prefix).map(String::from))
+}
+
+fn exitWithError(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
 fn main() {
-    let gameName = &*std::env::args().nth(1).expect("Game name????????????????");
-    //let gameName = "Metroid Fusion";
-    let mut gba = GBA::new(format!("ROMs/{}.gba", gameName));
+    let romPath = std::env::args().skip(1).find(|arg| !arg.starts_with("--")).unwrap_or_else(|| exitWithError(USAGE));
+    let config = match option("config") { // The default config file is optional, but one passed explicitly has to exist
+        Some(path) => Config::load(&path),
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => Config::load(DEFAULT_CONFIG_PATH),
+        None => Ok(Config::default())
+    }.unwrap_or_else(|e| exitWithError(&e));
+
+    let biosPath = if std::env::args().any(|arg| arg == "--hle-bios") { None } else { config.biosPath(option("bios")) };
+    if biosPath.is_none() {
+        println!("No BIOS image configured, using the HLE BIOS");
+    }
+
+    // For games that check which Flash chip they're running on, eg --flash-manufacturer=macronix
+    let flashManufacturer = option("flash-manufacturer").or_else(|| config.get("flash_manufacturer").map(String::from)).map(|name| {
+        FlashManufacturer::fromName(&name).unwrap_or_else(|| exitWithError(&format!("Unknown Flash manufacturer {}. Valid manufacturers: macronix, panasonic, sanyo, sst", name)))
+    });
+
+    let options = GBAOptions {
+        biosPath,
+        directBoot: std::env::args().any(|arg| arg == "--direct-boot"), // Skip the BIOS intro
+        patchPath: option("patch"), // IPS/UPS/BPS patch
+        flashManufacturer
+    };
+
+    let mut gba = GBA::new(&romPath, options).unwrap_or_else(|e| exitWithError(&e));
+    let gameName = Path::new(&romPath).file_stem().map_or(romPath.clone(), |name| name.to_string_lossy().to_string());
+
+    // For games that report the wrong save type, eg --save-type=flash128k. The config file can set it for a single game
+    // with the game code from the ROM header, eg save_type.AXVE = flash128k
+    let saveType = option("save-type")
+        .or_else(|| config.get(&format!("save_type.{}", gba.header().gameCode.to_lowercase())).map(String::from))
+        .or_else(|| config.get("save_type").map(String::from));
+    if let Some(name) = saveType {
+        let saveType = save_types::fromName(&name).unwrap_or_else(|| exitWithError(&format!("Unknown save type {}. Valid types: none, sram, eeprom, flash64k, flash128k", name)));
+        gba.set_save_type(saveType);
+    }
+
+    // Extra cheat file, on top of the .cht file next to the ROM
+    if let Some(path) = option("cheats") {
+        if let Err(e) = gba.load_cheats(&path) {
+            println!("Failed to load cheats from {}: {}", path, e);
+        }
+    }
+
+    for (i, cheat) in gba.cheats().iter().enumerate().take(CHEAT_KEYS.len()) {
+        println!("F{}: {}", i + 1, cheat.name);
+    }
     gba.init();
 
-    let mut window = RenderWindow::new(VideoMode::new(240, 160, 32),
+    let mut window = RenderWindow::new(VideoMode::new(WIDTH as u32, HEIGHT as u32, 32),
                             &format!("Beeg Advanced: {}", gameName),
                             Style::RESIZE | Style::CLOSE,
                   &ContextSettings::default());
     window.set_framerate_limit(0); // fun fact: SFML's frame limiting is crap
-    
+    let mut texture = Texture::new(WIDTH as u32, HEIGHT as u32).unwrap();
+    let mut solarLevel = 0_u8; // Light level for the Boktai solar sensor, changed with PageUp/PageDown
+
     loop {
-        gba.executeFrame(&mut window);
+        gba.run_frame();
+        gba.set_keys(pollKeys()); // Update joypad
+        let (tiltX, tiltY) = pollTilt();
+        gba.set_tilt(tiltX, tiltY);
+        gba.set_gyro(axis(Key::U, Key::O)); // Rotate with U/O
+
+        if let Some(isRumbling) = gba.take_rumble_change() { // No gamepad support yet, so just log it
+            println!("Rumble {}", if isRumbling { "on" } else { "off" });
+        }
+
+        // poll window events and render screen
+        while let Some(event) = window.poll_event() {
+            match event {
+                Event::Closed => {
+                    gba.flush_save(); // exit() skips destructors, so save manually
+                    std::process::exit(0);
+                }
+
+                Event::KeyPressed { code: Key::PageUp, .. } => { solarLevel = solarLevel.saturating_add(0x10); gba.set_solar_level(solarLevel) }
+                Event::KeyPressed { code: Key::PageDown, .. } => { solarLevel = solarLevel.saturating_sub(0x10); gba.set_solar_level(solarLevel) }
+                Event::KeyPressed { code, .. } if CHEAT_KEYS.contains(&code) => {
+                    let index = CHEAT_KEYS.iter().position(|&key| key == code).unwrap();
+                    if let Some(cheat) = gba.cheats().get(index) {
+                        let enabled = !cheat.enabled;
+                        println!("{} {}", cheat.name, if enabled { "on" } else { "off" });
+                        gba.set_cheat_enabled(index, enabled);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let sprite: Sprite;
+        unsafe {
+            texture.update_from_pixels(gba.framebuffer(), WIDTH as u32, HEIGHT as u32, 0, 0);
+            sprite = Sprite::with_texture(&texture);
+        }
+
+        // It's not necessary to clear the window since we're redrawing the whole thing anyways
+        window.draw(&sprite);
+        window.display();
     }
-}
\ No newline at end of file
+}
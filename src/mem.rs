@@ -1,4 +1,11 @@
 use crate::helpers::readFileIntoVec;
+use crate::BIOS::bios::{buildHLEBios, BIOS_SIZE};
+use crate::cartridge::{Backup, save_types, CartridgeHeader, HEADER_SIZE};
+use crate::patch::applyPatch;
+use crate::loader::{readROM, Program};
+use crate::flash::FlashManufacturer;
+use crate::gba::GBAOptions;
+use std::path::{Path, PathBuf};
 
 pub struct Memory {
 // main. non-IO memory
@@ -6,27 +13,148 @@ pub struct Memory {
     pub ROM:   Vec <u8>,
     pub eWRAM: Vec <u8>,
     pub iWRAM: Vec <u8>,
-    pub SRAM:  Vec <u8>
+    pub backup: Backup,
+    pub flashManufacturer: Option<FlashManufacturer>, // Overrides the manufacturer ID of Flash chips. None = whatever real carts with that chip use
+    pub romSize: usize, // Size of the ROM before padding
+    pub entryPoint: u32, // Where execution starts when the BIOS is skipped. 0x08000000 unless the program runs from RAM
+    pub header: CartridgeHeader,
+    pub savePath: Option<PathBuf>, // The .sav file next to the ROM, or next to the patch if one was applied
+    pub isBackupDirty: bool, // Whether backup memory was written to since the last flush
+
+    pub isHLEBios: bool // Whether SWIs are handled natively instead of by a BIOS image
 }
 
 impl Memory {
-    pub fn new(romPath: String) -> Memory {
-        let mut ROM = readFileIntoVec(&romPath);
-        let mut BIOS = readFileIntoVec(&"ROMs/NormattBIOS.gba".to_string());
-        let mut len = ROM.len()-1;
-
-        while len < (32 * 1024 * 1024) {// While the ROM is < 32 MB, fill it with valid OoB data 
-            let OoBFillerData = (len >> 1) as u16;
-            ROM.push((OoBFillerData >> ((len & 1) << 3)) as u8);
-            len += 1;
+    // Load a ROM file (or a .zip containing one, a multiboot image or an ELF file) and a BIOS image file.
+    // If no patch is given, an IPS/UPS/BPS patch with the same name as the ROM is applied if there is one
+    pub fn new(romPath: &str, options: &GBAOptions) -> Result<Memory, String> {
+        let patchPath = options.patchPath.as_ref().map(PathBuf::from).or_else(|| {
+            ["ips", "ups", "bps"].iter().map(|ext| Path::new(romPath).with_extension(ext)).find(|path| path.exists())
+        });
+
+        let mut ROM = readROM(romPath)?;
+        if let Some(path) = &patchPath { // Patch before padding, since patches can change the ROM size
+            let patch = readFileIntoVec(&path.to_string_lossy())?;
+            ROM = applyPatch(ROM, &patch).map_err(|e| format!("Failed to apply patch {}: {}", path.display(), e))?;
+            println!("Applied patch {}", path.display());
+        }
+
+        let BIOS = match &options.biosPath {
+            Some(path) => {
+                let BIOS = readFileIntoVec(path)?;
+                if BIOS.len() != BIOS_SIZE {
+                    return Err(format!("{} is {} bytes long, but the GBA BIOS is 16KB", path, BIOS.len()));
+                }
+
+                Some(BIOS)
+            }
+
+            None => None
+        };
+
+        let savePath = patchPath.as_deref().unwrap_or(Path::new(romPath)).with_extension("sav"); // Hacks get their own save
+        Program::parse(romPath, ROM).and_then(|program| Memory::fromProgram(program, BIOS, Some(savePath), options.flashManufacturer))
+                                    .map_err(|e| format!("Invalid ROM {}: {}", romPath, e))
+    }
+
+    // If there's no BIOS image, the HLE BIOS is used. Without a save path, backup memory isn't saved to disk
+    pub fn fromBytes(ROM: Vec<u8>, BIOS: Option<Vec<u8>>, savePath: Option<PathBuf>) -> Result<Memory, String> {
+        Memory::fromProgram(Program::cartridge(ROM), BIOS, savePath, None)
+    }
+
+    pub fn fromProgram(program: Program, BIOS: Option<Vec<u8>>, savePath: Option<PathBuf>, flashManufacturer: Option<FlashManufacturer>) -> Result<Memory, String> {
+        let mut ROM = program.rom;
+        if ROM.len() > 32 * 1024 * 1024 {
+            return Err(format!("ROM is {} bytes long, but GBA ROMs can be at most 32MB", ROM.len()));
+        }
+
+        if let Some(BIOS) = &BIOS {
+            if BIOS.len() != BIOS_SIZE {
+                return Err(format!("BIOS is {} bytes long, but the GBA BIOS is 16KB", BIOS.len()));
+            }
+        }
+
+        let isCartridge = !ROM.is_empty();
+        let (header, saveType) = if isCartridge {
+            let header = CartridgeHeader::parse(&ROM)?;
+            for warning in header.validate(&ROM) {
+                println!("Warning: {}", warning);
+            }
+
+            (header, save_types::detect(&ROM))
+        } else { // Programs running from RAM have no cartridge, but multiboot images still start with a header
+            let mut headerData: Vec<u8> = program.segments.first().map_or(vec![], |segment| segment.data.iter().take(HEADER_SIZE).copied().collect());
+            headerData.resize(HEADER_SIZE, 0);
+            (CartridgeHeader::parse(&headerData)?, save_types::None)
+        };
+
+        let romSize = ROM.len();
+        for address in romSize..(32 * 1024 * 1024) { // While the ROM is < 32 MB, fill it with valid OoB data
+            let OoBFillerData = (address >> 1) as u16;
+            ROM.push((OoBFillerData >> ((address & 1) << 3)) as u8);
         }
 
-        Memory {
-            BIOS,
+        let mut mem = Memory {
+            isHLEBios: BIOS.is_none(),
+            BIOS: BIOS.unwrap_or_else(buildHLEBios),
             ROM,
             eWRAM: vec![0; 256 * 1024],
             iWRAM: vec![0; 32 * 1024],
-            SRAM:  vec![0; 64 * 1024]
+            backup: Backup::new(saveType, flashManufacturer),
+            flashManufacturer,
+            romSize,
+            entryPoint: program.entryPoint,
+            header,
+            savePath,
+            isBackupDirty: false
+        };
+
+        for segment in &program.segments {
+            mem.loadSegment(segment.address, &segment.data)?;
+        }
+
+        mem.loadSave();
+        Ok(mem)
+    }
+
+    fn loadSegment(&mut self, address: u32, data: &[u8]) -> Result<(), String> {
+        let (region, offset) = match address >> 24 {
+            2 => (&mut self.eWRAM, address as usize - 0x02000000),
+            3 => (&mut self.iWRAM, address as usize - 0x03000000),
+            _ => return Err(format!("Can't load data to {:08X}, only to EWRAM and IWRAM", address))
+        };
+
+        let dest = region.get_mut(offset..offset + data.len()).ok_or(format!("Data at {:08X} doesn't fit in RAM", address))?;
+        dest.copy_from_slice(data);
+        Ok(())
+    }
+
+    // Replace the detected backup memory, for games that report the wrong save type
+    pub fn setSaveType(&mut self, saveType: save_types) {
+        self.backup = Backup::new(saveType, self.flashManufacturer);
+        self.isBackupDirty = false;
+        self.loadSave();
+    }
+
+    fn loadSave(&mut self) {
+        if let Some(savePath) = self.savePath.as_ref().filter(|path| path.exists()) {
+            match readFileIntoVec(&savePath.to_string_lossy()) {
+                Ok(data) => self.backup.loadData(&data, self.flashManufacturer),
+                Err(e) => println!("{}, starting with empty backup memory", e)
+            }
+        }
+    }
+
+    // Write backup memory to the .sav file if it has changed
+    pub fn flushSave(&mut self) {
+        let savePath = match &self.savePath {
+            Some(path) if self.isBackupDirty && !self.backup.getData().is_empty() => path,
+            _ => return
+        };
+
+        match std::fs::write(savePath, self.backup.getData()) {
+            Ok(_) => self.isBackupDirty = false,
+            Err(e) => println!("Failed to write save file {}: {}", savePath.display(), e)
         }
     }
-}
\ No newline at end of file
+}
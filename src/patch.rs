@@ -0,0 +1,227 @@
+// ROM soft-patching. Patches are applied when the ROM is loaded, so the file on disk is never modified
+
+const PATCH_FOOTER_SIZE: usize = 12; // UPS and BPS patches end with the source, target and patch CRC32s
+
+pub fn applyPatch(rom: Vec<u8>, patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.starts_with(b"PATCH") {
+        applyIPS(rom, patch)
+    } else if patch.starts_with(b"UPS1") {
+        applyUPS(&rom, patch)
+    } else if patch.starts_with(b"BPS1") {
+        applyBPS(&rom, patch)
+    } else {
+        Err("Unknown patch format, expected IPS, UPS or BPS".to_string())
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFF_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if (crc & 1) != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> PatchReader<'a> {
+    fn readByte(&mut self) -> Result<u8, String> {
+        let byte = *self.data.get(self.pos).ok_or("Patch ended unexpectedly")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn readBytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(count).ok_or("Patch ended unexpectedly")?;
+        let bytes = self.data.get(self.pos..end).ok_or("Patch ended unexpectedly")?;
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    // Big endian number, used by IPS
+    fn readBE(&mut self, count: usize) -> Result<usize, String> {
+        Ok(self.readBytes(count)?.iter().fold(0, |val, &byte| (val << 8) | byte as usize))
+    }
+
+    // Variable-length number used by UPS and BPS. 7 bits per byte, with bit 7 set on the last byte
+    fn readVarint(&mut self) -> Result<usize, String> {
+        let overflow = || "Malformed patch: varint overflow".to_string();
+        let mut val = 0_usize;
+        let mut shift = 1_usize;
+
+        loop {
+            let byte = self.readByte()?;
+            val = ((byte & 0x7F) as usize).checked_mul(shift).and_then(|digit| val.checked_add(digit)).ok_or_else(overflow)?;
+            if (byte & 0x80) != 0 {
+                return Ok(val);
+            }
+
+            // checked_shl only catches shift amounts past the width, so multiply to catch bits being shifted out
+            shift = shift.checked_mul(0x80).ok_or_else(overflow)?;
+            val = val.checked_add(shift).ok_or_else(overflow)?;
+        }
+    }
+
+    fn readCRC(&mut self) -> Result<u32, String> {
+        let bytes = self.readBytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+// UPS and BPS store the expected CRC32s of the ROM before and after patching, and of the patch itself
+fn checkFooter(source: &[u8], target: &[u8], patch: &[u8]) -> Result<(), String> {
+    let mut reader = PatchReader { data: patch, pos: patch.len() - PATCH_FOOTER_SIZE };
+    let sourceCRC = reader.readCRC()?;
+    let targetCRC = reader.readCRC()?;
+    let patchCRC = reader.readCRC()?;
+
+    if crc32(&patch[..patch.len() - 4]) != patchCRC {
+        return Err("Patch is corrupted (patch checksum mismatch)".to_string());
+    }
+
+    if crc32(source) != sourceCRC {
+        return Err(format!("Patch is for a different ROM (ROM checksum is {:08X}, expected {:08X})", crc32(source), sourceCRC));
+    }
+
+    if crc32(target) != targetCRC {
+        return Err("Patched ROM checksum mismatch".to_string());
+    }
+
+    Ok(())
+}
+
+fn applyIPS(mut rom: Vec<u8>, patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = PatchReader { data: patch, pos: 5 };
+
+    loop {
+        let offset = reader.readBE(3)?;
+        if offset == 0x454F46 { // "EOF"
+            break;
+        }
+
+        let size = reader.readBE(2)?;
+        let (size, bytes) = if size == 0 { // RLE record: a run of the same byte
+            let size = reader.readBE(2)?;
+            (size, vec![reader.readByte()?; size])
+        } else {
+            (size, reader.readBytes(size)?.to_vec())
+        };
+
+        if rom.len() < offset + size {
+            rom.resize(offset + size, 0);
+        }
+
+        rom[offset..offset + size].copy_from_slice(&bytes);
+    }
+
+    if let Ok(newSize) = reader.readBE(3) { // Optional truncation extension
+        rom.truncate(newSize);
+    }
+
+    Ok(rom)
+}
+
+fn applyUPS(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.len() < 4 + PATCH_FOOTER_SIZE {
+        return Err("Patch is too small".to_string());
+    }
+
+    let mut reader = PatchReader { data: &patch[..patch.len() - PATCH_FOOTER_SIZE], pos: 4 };
+    let sourceSize = reader.readVarint()?;
+    let targetSize = reader.readVarint()?;
+    if sourceSize != source.len() {
+        return Err(format!("Patch is for a {} byte ROM, but this one is {} bytes", sourceSize, source.len()));
+    }
+
+    let mut target = source.to_vec();
+    target.resize(targetSize, 0);
+    let mut pos = 0;
+
+    while reader.pos < reader.data.len() { // Each block skips some bytes, then XORs bytes in until it hits a 0
+        pos += reader.readVarint()?;
+        loop {
+            let byte = reader.readByte()?;
+            pos += 1;
+            if byte == 0 {
+                break;
+            }
+
+            if pos - 1 < targetSize {
+                target[pos - 1] ^= byte;
+            }
+        }
+    }
+
+    checkFooter(source, &target, patch)?;
+    Ok(target)
+}
+
+fn applyBPS(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.len() < 4 + PATCH_FOOTER_SIZE {
+        return Err("Patch is too small".to_string());
+    }
+
+    let mut reader = PatchReader { data: &patch[..patch.len() - PATCH_FOOTER_SIZE], pos: 4 };
+    let sourceSize = reader.readVarint()?;
+    let targetSize = reader.readVarint()?;
+    let metadataSize = reader.readVarint()?;
+    reader.readBytes(metadataSize)?;
+
+    if sourceSize != source.len() {
+        return Err(format!("Patch is for a {} byte ROM, but this one is {} bytes", sourceSize, source.len()));
+    }
+
+    let mut target = Vec::with_capacity(targetSize);
+    let mut sourceOffset = 0_isize; // Relative offsets used by the copy commands
+    let mut targetOffset = 0_isize;
+    let outOfBounds = || "Patch reads out of bounds".to_string();
+
+    while reader.pos < reader.data.len() {
+        let data = reader.readVarint()?;
+        let length = (data >> 2) + 1;
+
+        match data & 3 {
+            0 => { // SourceRead: Copy from the same offset in the source
+                let start = target.len();
+                target.extend_from_slice(source.get(start..start + length).ok_or_else(outOfBounds)?);
+            }
+
+            1 => target.extend_from_slice(reader.readBytes(length)?), // TargetRead: Copy from the patch
+
+            command => { // SourceCopy/TargetCopy: Copy from a relative offset in the source or the target
+                let offset = reader.readVarint()?;
+                let offset = if (offset & 1) != 0 { -((offset >> 1) as isize) } else { (offset >> 1) as isize };
+
+                if command == 2 {
+                    sourceOffset += offset;
+                    let start = sourceOffset as usize;
+                    target.extend_from_slice(source.get(start..start + length).ok_or_else(outOfBounds)?);
+                    sourceOffset += length as isize;
+                }
+
+                else {
+                    targetOffset += offset;
+                    for _ in 0..length { // Byte by byte, since the copy can overlap with what it's writing
+                        let byte = *target.get(targetOffset as usize).ok_or_else(outOfBounds)?;
+                        target.push(byte);
+                        targetOffset += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    if target.len() != targetSize {
+        return Err(format!("Patched ROM is {} bytes, expected {}", target.len(), targetSize));
+    }
+
+    checkFooter(source, &target, patch)?;
+    Ok(target)
+}
@@ -0,0 +1,98 @@
+use crate::bus::Bus;
+
+const PREFETCH_BUFFER_SIZE: u32 = 8; // The Game Pak prefetch buffer holds up to 8 halfwords
+
+pub struct Prefetcher {
+    pub enabled: bool,    // WAITCNT bit 14
+    pub active: bool,     // Whether the prefetcher is currently fetching from ROM
+    pub headAddress: u32, // Address of the oldest halfword in the buffer
+    pub count: u32,       // Amount of halfwords in the buffer
+    pub cycles: u32       // Cycles already spent fetching the next halfword
+}
+
+impl Prefetcher {
+    pub fn new() -> Prefetcher {
+        Prefetcher {
+            enabled: false,
+            active: false,
+            headAddress: 0,
+            count: 0,
+            cycles: 0
+        }
+    }
+
+    // Empty the buffer and start prefetching from a new address
+    pub fn restart(&mut self, address: u32) {
+        self.active = self.enabled;
+        self.headAddress = address;
+        self.count = 0;
+        self.cycles = 0;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.count = 0;
+        self.cycles = 0;
+    }
+}
+
+impl Bus {
+    // Opcode fetches. These go through the prefetch buffer when executing from ROM
+    #[inline(always)]
+    pub fn fetch16 (&mut self, address: u32) -> u16 {
+        self.isCodeFetch = true;
+        let val = self.read16(address);
+        self.isCodeFetch = false;
+        val
+    }
+
+    #[inline(always)]
+    pub fn fetch32 (&mut self, address: u32) -> u32 {
+        self.isCodeFetch = true;
+        let val = self.read32(address);
+        self.isCodeFetch = false;
+        val
+    }
+
+    // Let the prefetcher use cycles in which the CPU isn't accessing the Game Pak to fill the buffer
+    pub fn runPrefetcher (&mut self, cycles: u32) {
+        if !self.prefetcher.active || self.prefetcher.count == PREFETCH_BUFFER_SIZE {
+            return;
+        }
+
+        let nextAddress = self.prefetcher.headAddress + self.prefetcher.count * 2;
+        let fetchCycles = self.waitstates.seq16[((nextAddress >> 24) & 0xF) as usize];
+        self.prefetcher.cycles += cycles;
+
+        while self.prefetcher.cycles >= fetchCycles && self.prefetcher.count < PREFETCH_BUFFER_SIZE {
+            self.prefetcher.cycles -= fetchCycles;
+            self.prefetcher.count += 1;
+        }
+
+        if self.prefetcher.count == PREFETCH_BUFFER_SIZE { // The prefetcher stalls once the buffer is full
+            self.prefetcher.cycles = 0;
+        }
+    }
+
+    // Cost of an opcode fetch from ROM while prefetching is enabled, or None if the opcode isn't in (or on its way to) the buffer
+    pub fn getPrefetchedFetchCycles (&mut self, address: u32, width: u32) -> Option<u32> {
+        if !self.prefetcher.active || address != self.prefetcher.headAddress {
+            return None;
+        }
+
+        let halfwords = width >> 1;
+        let mut cycles = halfwords; // Reading a halfword out of the buffer takes 1 cycle
+
+        if self.prefetcher.count < halfwords { // Part of the opcode is still being fetched, so wait for it
+            let fetchCycles = self.waitstates.seq16[((address >> 24) & 0xF) as usize];
+            let missing = halfwords - self.prefetcher.count;
+            cycles = missing * fetchCycles - self.prefetcher.cycles.min(fetchCycles) + self.prefetcher.count;
+            self.prefetcher.cycles = 0;
+            self.prefetcher.count = halfwords;
+        }
+
+        self.prefetcher.headAddress += width;
+        self.prefetcher.count -= halfwords;
+        Some(cycles)
+    }
+}
@@ -0,0 +1,244 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, Timelike};
+use crate::isBitSet;
+
+// Seiko S-3511 real-time clock, connected to the cartridge GPIO port.
+// Pin 0 is the serial clock (SCK), pin 1 is serial data (SIO) and pin 2 is chip select (CS).
+// Bits are clocked on the rising edge of SCK, LSB first.
+
+const CONTROL_24_HOUR: u8 = 6;   // Control register bit selecting 24-hour mode
+const CONTROL_MINUTE_IRQ: u8 = 3; // Control register bit enabling the per-minute interrupt
+
+// RTC commands, from bits 4-6 of the command byte
+const CMD_RESET: u8 = 0;
+const CMD_DATETIME: u8 = 2;
+const CMD_FORCE_IRQ: u8 = 3;
+const CMD_CONTROL: u8 = 4;
+const CMD_TIME: u8 = 6;
+
+// Where the RTC gets the current time from
+#[derive(Clone, Copy, Debug)]
+pub enum TimeSource {
+    Host,                 // The host's local time
+    Fixed(NaiveDateTime), // Always the same time, so runs are reproducible
+    Offset(Duration)      // The host's local time, shifted by some amount
+}
+
+impl TimeSource {
+    pub fn now(&self) -> NaiveDateTime {
+        match self {
+            TimeSource::Host => Local::now().naive_local(),
+            TimeSource::Fixed(time) => *time,
+            TimeSource::Offset(offset) => Local::now().naive_local() + *offset
+        }
+    }
+}
+
+pub struct RTC {
+    pub timeSource: TimeSource,
+    control: u8,
+
+    command: Option<u8>, // Command being executed, None while waiting for a command byte
+    isReading: bool,     // Whether data is being sent from the RTC to the GBA
+    data: [u8; 7],       // Bytes of the register being transferred
+    dataLength: usize,
+    byteIndex: usize,
+    bits: u8,            // Bits received so far for the current byte
+    bitsRead: u32,
+
+    sck: bool,           // Previous state of SCK, to detect rising edges
+    sio: bool,           // Bit the RTC is driving on SIO while reading
+    lastMinute: u32,     // For the per-minute interrupt
+    pub irqRequested: bool
+}
+
+fn toBCD(val: u32) -> u8 {
+    (((val / 10) << 4) | (val % 10)) as u8
+}
+
+fn fromBCD(val: u8) -> u32 {
+    (val >> 4) as u32 * 10 + (val & 0xF) as u32
+}
+
+impl RTC {
+    pub fn new(timeSource: TimeSource) -> RTC {
+        RTC {
+            timeSource,
+            control: 1 << CONTROL_24_HOUR,
+
+            command: None,
+            isReading: false,
+            data: [0; 7],
+            dataLength: 0,
+            byteIndex: 0,
+            bits: 0,
+            bitsRead: 0,
+
+            sck: false,
+            sio: false,
+            lastMinute: timeSource.now().minute(),
+            irqRequested: false
+        }
+    }
+
+    // The SIO pin, as driven by the RTC
+    pub fn readPins(&self) -> u8 {
+        (self.sio as u8) << 1
+    }
+
+    pub fn writePins(&mut self, pins: u8) {
+        let sck = isBitSet!(pins, 0);
+        let sio = isBitSet!(pins, 1);
+        let cs = isBitSet!(pins, 2);
+
+        if !cs { // Deselecting the chip ends the transfer
+            self.command = None;
+            self.isReading = false;
+            self.bits = 0;
+            self.bitsRead = 0;
+        }
+
+        else if !self.sck && sck { // Rising edge of SCK
+            if self.isReading {
+                self.sio = (self.data[self.byteIndex] >> self.bitsRead) & 1 != 0;
+                self.bitsRead += 1;
+
+                if self.bitsRead == 8 {
+                    self.bitsRead = 0;
+                    self.byteIndex += 1;
+                    if self.byteIndex == self.dataLength {
+                        self.isReading = false;
+                        self.command = None;
+                    }
+                }
+            }
+
+            else {
+                self.bits |= (sio as u8) << self.bitsRead;
+                self.bitsRead += 1;
+
+                if self.bitsRead == 8 {
+                    let byte = self.bits;
+                    self.bits = 0;
+                    self.bitsRead = 0;
+                    self.processByte(byte);
+                }
+            }
+        }
+
+        self.sck = sck;
+    }
+
+    fn processByte(&mut self, byte: u8) {
+        if let Some(command) = self.command { // Parameter byte of a write command
+            self.data[self.byteIndex] = byte;
+            self.byteIndex += 1;
+
+            if self.byteIndex == self.dataLength {
+                self.finishWrite(command);
+                self.command = None;
+            }
+
+            return;
+        }
+
+        // Command bytes are RCCC0110: the fixed 0110 code in bits 0-3, the command in bits 4-6 and the direction in bit 7
+        if (byte & 0xF) != 6 {
+            println!("Invalid RTC command byte: {:02X}", byte);
+            return;
+        }
+
+        let command = (byte >> 4) & 7;
+        let isReading = isBitSet!(byte, 7);
+        self.dataLength = match command {
+            CMD_DATETIME => 7,
+            CMD_TIME => 3,
+            CMD_CONTROL => 1,
+            _ => 0
+        };
+        self.byteIndex = 0;
+
+        match command {
+            CMD_RESET => self.control = 0,
+            CMD_FORCE_IRQ => self.irqRequested = true,
+            _ => {}
+        }
+
+        if self.dataLength == 0 {
+            return;
+        }
+
+        self.command = Some(command);
+        self.isReading = isReading;
+        if isReading {
+            self.latchRegister(command);
+        }
+    }
+
+    // Fill the transfer buffer with the register being read
+    fn latchRegister(&mut self, command: u8) {
+        let now = self.timeSource.now();
+        let mut hour = now.hour();
+        if !isBitSet!(self.control, CONTROL_24_HOUR) {
+            hour %= 12;
+        }
+
+        let hour = toBCD(hour) | (((now.hour() >= 12) as u8) << 7); // Bit 7 is the PM flag
+        let time = [hour, toBCD(now.minute()), toBCD(now.second())];
+
+        match command {
+            CMD_DATETIME => {
+                let date = [toBCD(now.year() as u32 % 100), toBCD(now.month()), toBCD(now.day()), toBCD(now.weekday().num_days_from_sunday())];
+                self.data[..4].copy_from_slice(&date);
+                self.data[4..].copy_from_slice(&time);
+            }
+
+            CMD_TIME => self.data[..3].copy_from_slice(&time),
+            _ => self.data[0] = self.control
+        }
+    }
+
+    fn finishWrite(&mut self, command: u8) {
+        let now = self.timeSource.now();
+        let (date, time) = match command {
+            CMD_CONTROL => {
+                self.control = self.data[0];
+                return;
+            }
+
+            CMD_DATETIME => {
+                let year = 2000 + fromBCD(self.data[0]) as i32;
+                (NaiveDate::from_ymd_opt(year, fromBCD(self.data[1]), fromBCD(self.data[2])), &self.data[4..7])
+            }
+
+            _ => (Some(now.date()), &self.data[0..3])
+        };
+
+        let mut hour = fromBCD(time[0] & 0x3F);
+        if !isBitSet!(self.control, CONTROL_24_HOUR) && isBitSet!(time[0], 7) {
+            hour += 12;
+        }
+
+        match date.and_then(|date| date.and_hms_opt(hour, fromBCD(time[1]), fromBCD(time[2]))) {
+            Some(newTime) => self.setTime(newTime),
+            None => println!("Game set the RTC to an invalid time")
+        }
+    }
+
+    // Keep the clock running from the new time
+    fn setTime(&mut self, time: NaiveDateTime) {
+        self.timeSource = match self.timeSource {
+            TimeSource::Fixed(_) => TimeSource::Fixed(time),
+            _ => TimeSource::Offset(time - Local::now().naive_local())
+        };
+    }
+
+    // Check for the per-minute interrupt. Called once per frame
+    pub fn update(&mut self) {
+        let minute = self.timeSource.now().minute();
+        if minute != self.lastMinute && isBitSet!(self.control, CONTROL_MINUTE_IRQ) {
+            self.irqRequested = true;
+        }
+
+        self.lastMinute = minute;
+    }
+}
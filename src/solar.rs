@@ -0,0 +1,46 @@
+use crate::isBitSet;
+
+// Boktai solar sensor, connected to the cartridge GPIO port.
+// The game resets a counter (pin 1), then clocks it (pin 0) until the sensor output (pin 3) goes high,
+// which happens once the counter passes a threshold that gets lower the more light there is.
+// Pin 2 is chip select for the Boktai ROM's RTC, and the sensor ignores the other pins while it's high
+
+pub struct SolarSensor {
+    pub lightLevel: u8, // 0 = darkness, 255 = direct sunlight
+    threshold: u8,      // Light level sampled on the last reset
+    counter: u8,
+    clock: bool         // Previous state of the clock pin, to detect rising edges
+}
+
+impl SolarSensor {
+    pub fn new() -> SolarSensor {
+        SolarSensor {
+            lightLevel: 0,
+            threshold: 0xFF,
+            counter: 0,
+            clock: false
+        }
+    }
+
+    pub fn readPins(&self) -> u8 {
+        ((self.counter >= self.threshold) as u8) << 3
+    }
+
+    pub fn writePins(&mut self, pins: u8) {
+        if isBitSet!(pins, 2) {
+            return;
+        }
+
+        if isBitSet!(pins, 1) { // Reset the counter and sample the light level
+            self.counter = 0;
+            self.threshold = 0xFF - self.lightLevel;
+        }
+
+        let clock = isBitSet!(pins, 0);
+        if clock && !self.clock {
+            self.counter = self.counter.saturating_add(1);
+        }
+
+        self.clock = clock;
+    }
+}
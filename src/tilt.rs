@@ -0,0 +1,65 @@
+// 2-axis accelerometer used by Yoshi Topsy-Turvy and Koro Koro Puzzle, mapped into the backup memory area.
+// Writing 0x55 to 0x0E008000 and then 0xAA to 0x0E008100 latches a sample, which can then be read back
+// as 12-bit X and Y values from 0x0E008200-0x0E008500
+
+use crate::cartridge::CartridgeHeader;
+
+const TILT_GAMES: [&str; 2] = ["KYG", "KHP"]; // Yoshi Topsy-Turvy, Koro Koro Puzzle
+const CENTER: i32 = 0x3A0; // Reading when the GBA is held flat
+const RANGE: f32 = 0x200 as f32; // Reading difference at maximum tilt
+
+pub struct TiltSensor {
+    pub x: f32, // Tilt set by the host, from -1.0 (left/up) to 1.0 (right/down)
+    pub y: f32,
+    sampleX: u16,
+    sampleY: u16,
+    isLatching: bool // Got the first half of the sample command
+}
+
+impl TiltSensor {
+    pub fn new() -> TiltSensor {
+        TiltSensor {
+            x: 0.0,
+            y: 0.0,
+            sampleX: CENTER as u16,
+            sampleY: CENTER as u16,
+            isLatching: false
+        }
+    }
+
+    // The sensor is only on these carts, so look at the game code in the header
+    pub fn isPresent(header: &CartridgeHeader) -> bool {
+        TILT_GAMES.iter().any(|code| header.gameCode.starts_with(code))
+    }
+
+    pub fn isTiltAddress(address: u32) -> bool {
+        (0x0E008000..=0x0E0085FF).contains(&address)
+    }
+
+    pub fn read(&self, address: u32) -> u8 {
+        match address & 0xFF00 {
+            0x8200 => self.sampleX as u8,
+            0x8300 => ((self.sampleX >> 8) as u8 & 0xF) | 0x80, // Bit 7: Sample ready
+            0x8400 => self.sampleY as u8,
+            0x8500 => (self.sampleY >> 8) as u8 & 0xF,
+            _ => 0
+        }
+    }
+
+    pub fn write(&mut self, address: u32, val: u8) {
+        match (address & 0xFF00, val) {
+            (0x8000, 0x55) => self.isLatching = true,
+            (0x8100, 0xAA) if self.isLatching => {
+                self.isLatching = false;
+                self.sampleX = Self::toReading(self.x);
+                self.sampleY = Self::toReading(self.y);
+            }
+
+            _ => println!("Unknown tilt sensor write: {:02X} to {:08X}", val, address)
+        }
+    }
+
+    fn toReading(tilt: f32) -> u16 {
+        (CENTER - (tilt.max(-1.0).min(1.0) * RANGE) as i32) as u16 & 0xFFF
+    }
+}
@@ -0,0 +1,132 @@
+use crate::bus::Bus;
+use crate::isBitSet;
+
+const FIRST_ACCESS_WAITSTATES: [u32; 4] = [4, 3, 2, 8]; // SRAM and WS0-2 non-sequential wait states, indexed by their WAITCNT field
+const WS0_SECOND_ACCESS: [u32; 2] = [2, 1]; // WS0-2 sequential wait states, indexed by their WAITCNT bit
+const WS1_SECOND_ACCESS: [u32; 2] = [4, 1];
+const WS2_SECOND_ACCESS: [u32; 2] = [8, 1];
+
+// Access timings for each memory region (indexed by address >> 24), in cycles, including the wait states
+pub struct Waitstates {
+    pub nonSeq16: [u32; 16], // Cycles for non-sequential 8/16-bit accesses
+    pub seq16:    [u32; 16], // Cycles for sequential 8/16-bit accesses
+    pub nonSeq32: [u32; 16], // Cycles for non-sequential 32-bit accesses
+    pub seq32:    [u32; 16], // Cycles for sequential 32-bit accesses
+
+    pub nextSeqAddress: u32, // An access to this address continues the previous one, so it's sequential
+    pub pendingCycles: u64   // Cycles spent since the scheduler was last advanced
+}
+
+impl Waitstates {
+    pub fn new() -> Waitstates {
+        let mut waitstates = Waitstates {
+            //         BIOS  -  EWRAM IWRAM IO Pal VRAM OAM ROM (WS0-WS2)  SRAM
+            nonSeq16: [1, 1, 3, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+            seq16:    [1, 1, 3, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+            nonSeq32: [1, 1, 6, 1, 1, 2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+            seq32:    [1, 1, 6, 1, 1, 2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+
+            nextSeqAddress: 0,
+            pendingCycles: 0
+        };
+
+        waitstates.update(0);
+        waitstates
+    }
+
+    // Recalculate the Game Pak timings after a WAITCNT write
+    pub fn update(&mut self, waitcnt: u16) {
+        let waitcnt = waitcnt as usize;
+        let sram = FIRST_ACCESS_WAITSTATES[waitcnt & 3] + 1;
+        let nonSeq = [FIRST_ACCESS_WAITSTATES[(waitcnt >> 2) & 3] + 1,
+                      FIRST_ACCESS_WAITSTATES[(waitcnt >> 5) & 3] + 1,
+                      FIRST_ACCESS_WAITSTATES[(waitcnt >> 8) & 3] + 1];
+        let seq = [WS0_SECOND_ACCESS[(waitcnt >> 4) & 1] + 1,
+                   WS1_SECOND_ACCESS[(waitcnt >> 7) & 1] + 1,
+                   WS2_SECOND_ACCESS[(waitcnt >> 10) & 1] + 1];
+
+        for ws in 0..3 { // Each wait state region is mirrored twice, at 0x8/0x9, 0xA/0xB and 0xC/0xD
+            for region in (8 + ws * 2)..(10 + ws * 2) {
+                self.nonSeq16[region] = nonSeq[ws];
+                self.seq16[region] = seq[ws];
+                self.nonSeq32[region] = nonSeq[ws] + seq[ws]; // The Game Pak bus is 16-bit, so 32-bit accesses are split in 2
+                self.seq32[region] = seq[ws] * 2;
+            }
+        }
+
+        for region in 0xE..=0xF { // SRAM has an 8-bit bus and no sequential accesses
+            self.nonSeq16[region] = sram;
+            self.seq16[region] = sram;
+            self.nonSeq32[region] = sram;
+            self.seq32[region] = sram;
+        }
+    }
+}
+
+impl Bus {
+    // Charge the cost of a bus access. Accesses that continue where the previous one left off are sequential
+    #[inline(always)]
+    pub fn addAccessCycles(&mut self, address: u32, width: u32) {
+        let region = ((address >> 24) & 0xF) as usize;
+        let isROM = (8..=0xD).contains(&region);
+        let mut isSequential = address == self.waitstates.nextSeqAddress;
+
+        if isROM && self.isCodeFetch {
+            if let Some(cycles) = self.getPrefetchedFetchCycles(address, width) { // Opcode was served by the prefetch buffer
+                self.waitstates.pendingCycles += cycles as u64;
+                self.waitstates.nextSeqAddress = address.wrapping_add(width);
+                return;
+            }
+        }
+
+        if isROM && (address & 0x1FFFF) == 0 { // Crossing a 128KB ROM page always forces a non-sequential access
+            isSequential = false;
+        }
+
+        let cycles = match (width == 4, isSequential) {
+            (false, false) => self.waitstates.nonSeq16[region],
+            (false, true)  => self.waitstates.seq16[region],
+            (true, false)  => self.waitstates.nonSeq32[region],
+            (true, true)   => self.waitstates.seq32[region]
+        };
+
+        self.waitstates.pendingCycles += cycles as u64;
+        self.waitstates.nextSeqAddress = address.wrapping_add(width);
+
+        if !isROM { // The Game Pak bus is free, so the prefetcher can keep fetching
+            self.runPrefetcher(cycles);
+        }
+
+        else if self.isCodeFetch { // Prefetch buffer miss, start prefetching after the opcode
+            self.prefetcher.restart(address.wrapping_add(width));
+        }
+
+        else { // Data accesses to ROM stop the prefetcher and throw away its buffer
+            self.prefetcher.stop();
+        }
+    }
+
+    // Charge internal (I) cycles, where the CPU is busy but not using the bus
+    #[inline(always)]
+    pub fn addIdleCycles(&mut self, cycles: u32) {
+        self.waitstates.pendingCycles += cycles as u64;
+        self.runPrefetcher(cycles);
+    }
+
+    // Get the cycles spent since the last call, so they can be fed to the scheduler
+    pub fn takePendingCycles(&mut self) -> u64 {
+        let cycles = self.waitstates.pendingCycles;
+        self.waitstates.pendingCycles = 0;
+        cycles
+    }
+
+    pub fn writeWAITCNT(&mut self, val: u16) {
+        self.waitcnt = val & 0x5FFF; // Bit 15 (Game Pak type) is read-only
+        self.waitstates.update(self.waitcnt);
+
+        self.prefetcher.enabled = isBitSet!(val, 14);
+        if !self.prefetcher.enabled {
+            self.prefetcher.stop();
+        }
+    }
+}